@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use acme::{create_p256_key, order::ChallengeStore, Directory, DirectoryUrl};
+use actix_web::{get, middleware::Logger, web, App, HttpResponse, HttpServer};
+
+const DOMAINS: &[&str] = &["example.org"];
+const CONTACT_EMAIL: Option<&str> = None;
+
+/// Serves proofs from the challenge store instead of from files.
+#[get("/.well-known/acme-challenge/{token}")]
+async fn acme_challenge(
+    store: web::Data<ChallengeStore>,
+    token: web::Path<String>,
+) -> HttpResponse {
+    match store.respond(&token) {
+        Some(proof) => HttpResponse::Ok().body(proof),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[actix_web::main]
+async fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    // The store is shared between the HTTP server and the ACME client.
+    let store = ChallengeStore::new();
+
+    log::info!("starting temporary HTTP challenge server");
+    let srv = HttpServer::new({
+        let store = store.clone();
+
+        move || {
+            App::new()
+                .wrap(Logger::default().log_target("acme_http_server"))
+                .app_data(web::Data::new(store.clone()))
+                .service(acme_challenge)
+        }
+    })
+    .bind(("0.0.0.0", 80))?
+    .workers(1)
+    .disable_signals()
+    .shutdown_timeout(0)
+    .run();
+
+    let srv_handle = srv.handle();
+    let srv_task = actix_web::rt::spawn(srv);
+
+    log::info!("fetching LetsEncrypt directory");
+    // Note: Change to `DirectoryUrl::LetsEncrypt` in production.
+    let dir = Directory::fetch(DirectoryUrl::LetsEncryptStaging).await?;
+
+    let contact = CONTACT_EMAIL.map(|email| vec![format!("mailto:{email}")]);
+
+    log::info!("generating private key and registering with ACME provider");
+    let acc = dir.register_account(contact.clone()).await?;
+
+    log::info!("ordering a new TLS certificate for our domain");
+    let mut order = acc.new_order(DOMAINS[0], DOMAINS).await?;
+
+    log::info!("waiting for order to be validated");
+    let csr = loop {
+        if let Some(csr) = order.confirm_validations() {
+            log::info!("order validated");
+            break csr;
+        }
+
+        for auth in order.authorizations().await? {
+            // Publishes the proof in the store, validates the challenge, and removes the proof
+            // again, even if validation fails.
+            auth.solve_http(&store, Duration::from_secs(5)).await?;
+        }
+
+        order.refresh().await?;
+    };
+
+    let private_key = create_p256_key();
+
+    log::info!("submitting CSR for: {:?}", &csr.api_order().domains());
+    let ord_cert = csr.finalize(private_key, Duration::from_secs(5)).await?;
+
+    log::info!("downloading certificate");
+    let cert = ord_cert.download_cert().await?;
+
+    println!();
+    println!("{}", cert.certificate());
+
+    // Stop temporary ACME server.
+    srv_handle.stop(true).await;
+    srv_task.await??;
+
+    Ok(())
+}
@@ -1,26 +1,43 @@
-use std::{collections::HashSet, iter, sync::Arc};
+use std::{collections::HashSet, fmt, iter, sync::Arc, time::Duration};
 
 use base64::prelude::*;
 use eyre::eyre;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::Semaphore;
 use zeroize::Zeroizing;
 
 use crate::{
     api,
     cert::Certificate,
-    order::{NewOrder, Order},
-    req::req_expect_header,
+    jws::{jwk_thumbprint, jwk_value_thumbprint, JwsFormat},
+    order::{key_authorization, refresh_order, Auth, NewOrder, Order},
+    req::{req_expect_header, req_json, req_links},
     trans::Transport,
+    util, Error, KeyKind, PollConfig,
 };
 
 mod acme_key;
 
 pub(crate) use self::acme_key::AcmeKey;
 
+/// Generates a new account key of the given `kind`, returned PEM-encoded (PKCS #8).
+///
+/// Useful for generating account keys separately from registering them, e.g., in a dedicated
+/// secure environment. The key can be registered later using [`Directory::load_account()`].
+///
+/// [`Directory::load_account()`]: crate::Directory::load_account()
+pub fn generate_account_key(kind: KeyKind) -> eyre::Result<Zeroizing<String>> {
+    AcmeKey::new_of_kind(kind).to_pem()
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct AccountInner {
     pub transport: Transport,
     pub api_account: api::Account,
     pub api_directory: api::Directory,
+    pub poll_config: PollConfig,
+    pub order_limit: Option<Arc<Semaphore>>,
 }
 
 /// Account with an ACME provider.
@@ -28,12 +45,14 @@ pub(crate) struct AccountInner {
 /// Accounts are created using [`Directory::register_account()`] and consists of a contact email
 /// address and a private key for signing requests to the ACME API.
 ///
-/// This library uses elliptic curve P-256 for accessing the account.
+/// This library uses elliptic curve P-256 for accessing the account by default, or P-384 if
+/// configured using [`DirectoryBuilder::account_key_kind()`].
 ///
 /// The advantages of using elliptic curve cryptography are that the signed requests against the
 /// ACME lib are small and that the public key can be derived from the private key.
 ///
 /// [`Directory::register_account()`]: crate::Directory::register_account()
+/// [`DirectoryBuilder::account_key_kind()`]: crate::DirectoryBuilder::account_key_kind()
 #[derive(Debug, Clone)]
 pub struct Account {
     inner: Arc<AccountInner>,
@@ -44,23 +63,111 @@ impl Account {
         transport: Transport,
         api_account: api::Account,
         api_directory: api::Directory,
+        poll_config: PollConfig,
+        max_concurrent_orders: Option<usize>,
     ) -> Self {
         Self {
             inner: Arc::new(AccountInner {
                 transport,
                 api_account,
                 api_directory,
+                poll_config,
+                order_limit: max_concurrent_orders.map(|limit| Arc::new(Semaphore::new(limit))),
             }),
         }
     }
 
-    /// Private key for this account.
+    /// Private key for this account, PEM-encoded (PKCS #8).
     ///
-    /// The key is an elliptic curve private key.
+    /// The key is an elliptic curve private key. Reload the account from it using
+    /// [`Directory::load_existing_account()`], or use [`to_persisted()`](Self::to_persisted) to
+    /// also save the account URL for [`Directory::account_from_persisted()`].
+    ///
+    /// [`Directory::load_existing_account()`]: crate::Directory::load_existing_account()
+    /// [`Directory::account_from_persisted()`]: crate::Directory::account_from_persisted()
     pub fn acme_private_key_pem(&self) -> eyre::Result<Zeroizing<String>> {
         self.inner.transport.acme_key().to_pem()
     }
 
+    /// Returns the account URL, private key, and [server key thumbprint], for restoring this
+    /// account later using [`Directory::account_from_persisted()`].
+    ///
+    /// [server key thumbprint]: Self::server_key_thumbprint
+    /// [`Directory::account_from_persisted()`]: crate::Directory::account_from_persisted()
+    pub fn to_persisted(&self) -> eyre::Result<PersistedAccount> {
+        let acme_key = self.inner.transport.acme_key();
+
+        Ok(PersistedAccount {
+            account_url: acme_key.key_id().to_owned(),
+            private_key_pem: acme_key.to_pem()?,
+            server_key_thumbprint: self.server_key_thumbprint()?,
+        })
+    }
+
+    /// Returns the base64url-encoded JWK thumbprint ([RFC 7638]) of this account's key.
+    ///
+    /// [RFC 7638]: https://datatracker.ietf.org/doc/html/rfc7638
+    pub fn thumbprint(&self) -> eyre::Result<String> {
+        jwk_thumbprint(&self.inner.transport.acme_key())
+    }
+
+    /// Returns the base64url-encoded JWK thumbprint ([RFC 7638]) of the account key reported by the
+    /// ACME API, if it was included in the account object.
+    ///
+    /// Store this after first registering an account and check it on subsequent loads using
+    /// [`verify_server_key()`](Self::verify_server_key) (trust on first use).
+    ///
+    /// [RFC 7638]: https://datatracker.ietf.org/doc/html/rfc7638
+    pub fn server_key_thumbprint(&self) -> eyre::Result<Option<String>> {
+        self.inner
+            .api_account
+            .key
+            .as_ref()
+            .map(jwk_value_thumbprint)
+            .transpose()
+    }
+
+    /// Checks that the account key reported by the ACME API matches the `pinned` thumbprint
+    /// previously returned from [`server_key_thumbprint()`](Self::server_key_thumbprint).
+    ///
+    /// Detects the ACME API provider returning a different account than expected. Fails with
+    /// [`Error::AccountKeyMismatch`] if the thumbprints differ or if the ACME API did not report the
+    /// account key. After legitimately changing the account key, store the new thumbprint instead.
+    pub fn verify_server_key(&self, pinned: &str) -> eyre::Result<()> {
+        let found = self.server_key_thumbprint()?;
+
+        if found.as_deref() == Some(pinned) {
+            return Ok(());
+        }
+
+        Err(Error::AccountKeyMismatch {
+            expected: pinned.to_owned(),
+            found,
+        }
+        .into())
+    }
+
+    /// Returns the key authorization (`<token>.<thumbprint>`) for a challenge `token`.
+    ///
+    /// This is the same value as [`Challenge::http_proof()`], but allows serving `http-01`
+    /// responses from an existing request router without going through a challenge object.
+    ///
+    /// [`Challenge::http_proof()`]: crate::order::Challenge::http_proof()
+    pub fn key_authorization(&self, token: &str) -> eyre::Result<String> {
+        key_authorization(token, &self.inner.transport.acme_key(), false)
+    }
+
+    /// Returns the `dns-01` proof for a challenge `token`.
+    ///
+    /// This is the same value as [`Challenge::dns_proof()`], i.e.,
+    /// `base64url(sha256(<token>.<thumbprint>))`, but allows DNS records to be staged before
+    /// the challenge object has been obtained.
+    ///
+    /// [`Challenge::dns_proof()`]: crate::order::Challenge::dns_proof()
+    pub fn dns_proof_for_token(&self, token: &str) -> eyre::Result<String> {
+        key_authorization(token, &self.inner.transport.acme_key(), true)
+    }
+
     /// Create a new order to issue a certificate for this account.
     ///
     /// Each order has a required `primary_name` (which will be set as the certificates `CN`) and a
@@ -69,70 +176,352 @@ impl Account {
     /// This library doesn't constrain the number of `alt_names`, but it is limited by the ACME API
     /// provider. Let's Encrypt [sets a max of 100 names] per certificate.
     ///
+    /// Internationalized domain names are converted to their ASCII form using
+    /// [`util::domain_to_ascii()`]; the order's identifiers contain the converted names.
+    ///
     /// Every call creates a new order with the ACME API provider, even when the domain names
     /// supplied are exactly the same.
     ///
+    /// If the directory was configured with a [maximum number of concurrent orders], this waits
+    /// until an in-flight order is dropped before creating a new one.
+    ///
     /// [sets a max of 100 names]: https://letsencrypt.org/docs/rate-limits/
+    /// [`util::domain_to_ascii()`]: crate::util::domain_to_ascii()
+    /// [maximum number of concurrent orders]: crate::DirectoryBuilder::max_concurrent_orders()
     pub async fn new_order(
         &self,
         primary_name: &str,
         alt_names: &[&str],
+    ) -> eyre::Result<NewOrder> {
+        self.create_order(primary_name, alt_names, None).await
+    }
+
+    /// Create a new order, as in [`new_order()`](Self::new_order), sending `idempotency_key` in an
+    /// `Idempotency-Key` header.
+    ///
+    /// Some ACME gateways use this header to de-duplicate order creation, so that retrying after,
+    /// e.g., a network timeout does not create a second order. Use a unique key, such as one from
+    /// [`util::generate_idempotency_key()`], for each logical order and reuse it when retrying.
+    /// ACME API providers that do not support the header ignore it. The header is not sent by the
+    /// other methods creating orders.
+    ///
+    /// [`util::generate_idempotency_key()`]: crate::util::generate_idempotency_key()
+    pub async fn new_order_with_idempotency_key(
+        &self,
+        primary_name: &str,
+        alt_names: &[&str],
+        idempotency_key: &str,
+    ) -> eyre::Result<NewOrder> {
+        self.create_order(primary_name, alt_names, Some(idempotency_key))
+            .await
+    }
+
+    /// Create a new order, as in [`new_order()`](Self::new_order), and fetch its authorizations.
+    ///
+    /// Saves a step for the common case of orders with few domain names. For orders with many
+    /// domain names, prefer fetching [`NewOrder::authorizations()`] when needed.
+    pub async fn new_order_with_authorizations(
+        &self,
+        primary_name: &str,
+        alt_names: &[&str],
+    ) -> eyre::Result<(NewOrder, Vec<Auth>)> {
+        let order = self.new_order(primary_name, alt_names).await?;
+        let authorizations = order.authorizations().await?;
+        Ok((order, authorizations))
+    }
+
+    async fn create_order(
+        &self,
+        primary_name: &str,
+        alt_names: &[&str],
+        idempotency_key: Option<&str>,
     ) -> eyre::Result<NewOrder> {
         let mut identifiers = Vec::new();
         let mut domain_set = HashSet::new();
 
         for domain in iter::once(primary_name).chain(alt_names.iter().copied()) {
+            let domain = util::domain_to_ascii(domain)?;
+
             // de-duplicate identifiers list
-            if domain_set.insert(domain) {
+            if domain_set.insert(domain.clone()) {
                 // domain set did not contain `domain`
-                identifiers.push(api::Identifier::dns(domain));
+                identifiers.push(api::Identifier::dns(&domain));
             }
         }
 
         let order = api::Order::from_identifiers(identifiers);
 
+        let permit = match &self.inner.order_limit {
+            Some(order_limit) => Some(Arc::new(Arc::clone(order_limit).acquire_owned().await?)),
+            None => None,
+        };
+
         let new_order_url = self.inner.api_directory.new_order.as_str();
 
-        let res = self.inner.transport.call_kid(new_order_url, &order).await?;
+        let headers = idempotency_key
+            .map(|key| ("idempotency-key", key))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let res = self
+            .inner
+            .transport
+            .call_kid_with_headers(new_order_url, &order, &headers)
+            .await?;
         let order_url = req_expect_header(&res, "location")?;
-        let api_order = res.json::<api::Order>().await?;
+        let api_order = req_json::<api::Order>(res).await?;
 
         let mut order = Order::new(&self.inner, order, order_url);
         order.api_order.overwrite(api_order)?;
+        order.permit = permit;
         Ok(NewOrder { order })
     }
 
+    /// Create a new order to renew `previous`, for the same domain names.
+    ///
+    /// The first DNS name in the certificate's Subject Alternative Names is used as the primary
+    /// name. To keep the same certificate key (e.g., when the public key is pinned), finalize the
+    /// order using [`CsrOrder::finalize_renewal()`]:
+    ///
+    /// ```no_run
+    /// # async fn renew(acc: acme::Account, previous: acme::Certificate) -> eyre::Result<()> {
+    /// # use std::time::Duration;
+    /// let order = acc.renew(&previous).await?;
+    /// // ... prove ownership of the domains ...
+    /// # let csr = order.confirm_validations().unwrap();
+    /// let cert = csr
+    ///     .finalize_renewal(&previous, Duration::from_secs(5))
+    ///     .await?
+    ///     .download_cert()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`CsrOrder::finalize_renewal()`]: crate::order::CsrOrder::finalize_renewal()
+    pub async fn renew(&self, previous: &Certificate) -> eyre::Result<NewOrder> {
+        let names = previous.dns_names()?;
+
+        let (primary_name, alt_names) = names
+            .split_first()
+            .ok_or_else(|| eyre!("Certificate has no DNS names to renew"))?;
+        let alt_names = alt_names.iter().map(String::as_str).collect::<Vec<_>>();
+
+        self.new_order(primary_name, &alt_names).await
+    }
+
     /// Revoke a certificate for the reason given.
+    ///
+    /// The request is signed with the account key. To sign it with the certificate's own key
+    /// instead, use [`Directory::revoke_certificate_with_key()`].
+    ///
+    /// [`Directory::revoke_certificate_with_key()`]: crate::Directory::revoke_certificate_with_key()
     pub async fn revoke_certificate(
         &self,
         cert: &Certificate,
         reason: RevocationReason,
     ) -> eyre::Result<()> {
-        let cert_chain = cert.certificate_chain()?;
-        let cert_ee = cert_chain
-            .first()
-            .ok_or_else(|| eyre!("no certificates in chain"))?;
+        let revocation = revocation(cert, reason)?;
 
-        // convert to base64url of the DER (which is not PEM).
-        let certificate = BASE64_URL_SAFE_NO_PAD.encode(cert_ee);
+        let url = &self.inner.api_directory.revoke_cert;
+        self.inner.transport.call_kid(url, &revocation).await?;
 
-        let reason = match reason {
-            // > the reason code CRL entry extension SHOULD be absent instead of
-            // > using the unspecified (0) reasonCode value
-            // see <https://datatracker.ietf.org/doc/html/rfc5280#section-5.3.1>
-            RevocationReason::Unspecified => None,
+        Ok(())
+    }
+
+    /// Resumes the order at `url`, e.g., after a crash, using only the persisted order URL.
+    ///
+    /// The returned order reflects the current state of the order; use
+    /// [`NewOrder::confirm_validations()`] to progress it if its authorizations are already valid.
+    ///
+    /// Fails with [`Error::OrderInvalid`] if the order is invalid or with [`Error::OrderExpired`] if
+    /// it is pending or ready but has expired.
+    pub async fn order_from_url(&self, url: &str) -> eyre::Result<NewOrder> {
+        let order = refresh_order(&self.inner, url.to_owned(), "pending").await?;
+        order.check_resumable()?;
+
+        Ok(NewOrder { order })
+    }
+
+    /// Fetches the authorization that the challenge at `challenge_url` belongs to.
+    ///
+    /// The authorization is found by following the challenge's `Link: <url>;rel="up"` header, as
+    /// described in [RFC 8555 §7.5.1]. Useful for resuming work when only a challenge URL was
+    /// persisted; see also [`Auth::challenge_by_url()`].
+    ///
+    /// [RFC 8555 §7.5.1]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.5.1
+    pub async fn authorization_for_challenge(&self, challenge_url: &str) -> eyre::Result<Auth> {
+        let transport = &self.inner.transport;
 
-            reason => Some(reason as usize),
+        let res = transport.call_kid(challenge_url, &api::EmptyString).await?;
+
+        let auth_url = req_links(&res, "up")
+            .into_iter()
+            .next()
+            .ok_or_else(|| eyre!("Challenge {challenge_url} has no link to its authorization"))?;
+
+        let res = transport.call_kid(&auth_url, &api::EmptyString).await?;
+        let api_auth = req_json::<api::Authorization>(res).await?;
+
+        Ok(Auth::new(&self.inner, api_auth, &auth_url))
+    }
+
+    /// Fetches a single page of this account's orders list.
+    ///
+    /// Fetches the first page if `cursor` is `None`. To fetch the next page, pass the
+    /// [`next`](OrdersPage::next) URL of the previous page. See
+    /// [`list_all_orders()`](Self::list_all_orders) for fetching every page.
+    ///
+    /// Fails if the ACME API provider did not report an orders URL for this account.
+    pub async fn list_orders_page(&self, cursor: Option<&str>) -> eyre::Result<OrdersPage> {
+        let url = match cursor {
+            Some(cursor) => cursor,
+            None => self
+                .inner
+                .api_account
+                .orders
+                .as_deref()
+                .ok_or_else(|| eyre!("Account has no orders URL"))?,
         };
 
-        let revocation = api::Revocation::new(certificate, reason);
+        let res = self
+            .inner
+            .transport
+            .call_kid(url, &api::EmptyString)
+            .await?;
+        let next = req_links(&res, "next").into_iter().next();
+        let orders = req_json::<api::OrdersList>(res).await?.orders;
 
-        let url = &self.inner.api_directory.revoke_cert;
-        self.inner.transport.call_kid(url, &revocation).await?;
+        Ok(OrdersPage { orders, next })
+    }
+
+    /// Fetches the URLs of all of this account's orders, following pagination.
+    ///
+    /// Accounts with many orders may require many requests; use
+    /// [`list_orders_page()`](Self::list_orders_page) to control pagination. Fails if a page links
+    /// to a page that was already fetched, instead of looping forever.
+    pub async fn list_all_orders(&self) -> eyre::Result<Vec<String>> {
+        let mut orders = Vec::new();
+        let mut cursor = None;
+        let mut seen = HashSet::new();
+
+        loop {
+            let page = self.list_orders_page(cursor.as_deref()).await?;
+            orders.extend(page.orders);
+
+            match page.next {
+                Some(next) if !seen.insert(next.clone()) => {
+                    return Err(eyre!("Orders list page {next} was already fetched"));
+                }
+                Some(next) => cursor = Some(next),
+                None => return Ok(orders),
+            }
+        }
+    }
+
+    /// Deactivates the pending authorizations of this account's pending orders that expire within
+    /// `expiring_within`.
+    ///
+    /// Useful for staying below the ACME API provider's limits on pending authorizations. ACME
+    /// orders have no creation time, so their age can only be inferred from when they expire; e.g.,
+    /// Let's Encrypt orders expire 7 days after creation, so orders older than 1 day are those
+    /// expiring within 6 days.
+    ///
+    /// Authorizations that are already valid are left alone since they belong to the account and
+    /// may be reused by other orders for the same names. For the same reason, expiring ready
+    /// orders are only reported. Failing to clean up an order does not stop the others from being
+    /// cleaned up; such failures are reported too.
+    pub async fn cleanup_pending_orders(
+        &self,
+        expiring_within: Duration,
+    ) -> eyre::Result<PendingOrdersCleanup> {
+        let deadline = OffsetDateTime::now_utc() + expiring_within;
+        let mut cleanup = PendingOrdersCleanup::default();
+
+        for url in self.list_all_orders().await? {
+            if let Err(err) = self
+                .cleanup_pending_order(&url, deadline, &mut cleanup)
+                .await
+            {
+                cleanup.failed.push((url, err));
+            }
+        }
+
+        Ok(cleanup)
+    }
+
+    /// Cleans up the order at `url` if it expires before `deadline`, recording the outcome in
+    /// `cleanup`.
+    async fn cleanup_pending_order(
+        &self,
+        url: &str,
+        deadline: OffsetDateTime,
+        cleanup: &mut PendingOrdersCleanup,
+    ) -> eyre::Result<()> {
+        let order = refresh_order(&self.inner, url.to_owned(), "pending").await?;
+
+        if !order
+            .api_order
+            .expires_at()?
+            .is_some_and(|expires_at| expires_at <= deadline)
+        {
+            return Ok(());
+        }
+
+        match order.api_order.status {
+            Some(api::OrderStatus::Pending) => {}
+            Some(api::OrderStatus::Ready) => {
+                cleanup.ready_orders.push(url.to_owned());
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+
+        for mut auth in (NewOrder { order }).authorizations().await? {
+            if auth.api_auth().status == api::AuthorizationStatus::Pending {
+                auth.deactivate().await?;
+                cleanup.authorizations.push(auth.auth_url().to_owned());
+            }
+        }
+
+        cleanup.orders.push(url.to_owned());
 
         Ok(())
     }
 
+    /// Rolls this account over to the PEM-encoded (PKCS #8) private key `new_private_key_pem`, as
+    /// described in [RFC 8555 §7.3.5].
+    ///
+    /// On success, all further requests for this account, including those of existing orders, are
+    /// signed with the new key. The account URL is unchanged. The account's API object is not
+    /// refreshed, so it still reports the old key.
+    ///
+    /// [RFC 8555 §7.3.5]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5
+    pub async fn change_key(&self, new_private_key_pem: &str) -> eyre::Result<()> {
+        let new_key = AcmeKey::from_pem(new_private_key_pem)?;
+        let url = &self.inner.api_directory.key_change;
+
+        self.inner.transport.change_key(url, new_key).await
+    }
+
+    /// Signs `payload` as a request to `url` using this account's key, without sending it.
+    ///
+    /// This is a low-level escape hatch, useful for debugging requests or verifying them using
+    /// other tools. ACME API providers only accept [`JwsFormat::Flattened`].
+    ///
+    /// A nonce is consumed from the pool each time this is called.
+    pub async fn sign_request<T>(
+        &self,
+        url: &str,
+        payload: &T,
+        format: JwsFormat,
+    ) -> eyre::Result<String>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner.transport.sign_kid(url, payload, format).await
+    }
+
     /// Returns a reference to the account's API object.
     ///
     /// Useful for debugging.
@@ -141,6 +530,93 @@ impl Account {
     }
 }
 
+/// A page of an account's orders list.
+///
+/// Returned from [`Account::list_orders_page()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrdersPage {
+    /// URLs of the orders on this page.
+    pub orders: Vec<String>,
+
+    /// URL of the next page, from the `Link: <...>;rel="next"` header, if there is one.
+    pub next: Option<String>,
+}
+
+/// Builds the revocation request for the end-entity certificate of `cert`.
+pub(crate) fn revocation(
+    cert: &Certificate,
+    reason: RevocationReason,
+) -> eyre::Result<api::Revocation> {
+    let cert_chain = cert.certificate_chain()?;
+    let cert_ee = cert_chain
+        .first()
+        .ok_or_else(|| eyre!("no certificates in chain"))?;
+
+    // convert to base64url of the DER (which is not PEM).
+    let certificate = BASE64_URL_SAFE_NO_PAD.encode(cert_ee);
+
+    let reason = match reason {
+        // > the reason code CRL entry extension SHOULD be absent instead of
+        // > using the unspecified (0) reasonCode value
+        // see <https://datatracker.ietf.org/doc/html/rfc5280#section-5.3.1>
+        RevocationReason::Unspecified => None,
+
+        reason => Some(reason as usize),
+    };
+
+    Ok(api::Revocation::new(certificate, reason))
+}
+
+/// Account URL, private key, and pinned server key thumbprint of an [`Account`], for saving and
+/// restoring it between runs.
+///
+/// Returned from [`Account::to_persisted()`]. The private key is zeroized on drop and redacted from
+/// the `Debug` output, but is included when serialized, so store the serialized form securely.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedAccount {
+    /// The account URL, i.e., the key ID used to sign requests.
+    pub account_url: String,
+
+    /// The PEM-encoded (PKCS #8) private key of the account.
+    pub private_key_pem: Zeroizing<String>,
+
+    /// Thumbprint of the account key reported by the ACME API, as returned from
+    /// [`Account::server_key_thumbprint()`].
+    ///
+    /// If set, [`Directory::account_from_persisted()`] checks it as in
+    /// [`Account::verify_server_key()`].
+    ///
+    /// [`Directory::account_from_persisted()`]: crate::Directory::account_from_persisted()
+    #[serde(default)]
+    pub server_key_thumbprint: Option<String>,
+}
+
+impl fmt::Debug for PersistedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistedAccount")
+            .field("account_url", &self.account_url)
+            .field("private_key_pem", &"..")
+            .field("server_key_thumbprint", &self.server_key_thumbprint)
+            .finish()
+    }
+}
+
+/// Report of the orders cleaned up by [`Account::cleanup_pending_orders()`].
+#[derive(Debug, Default)]
+pub struct PendingOrdersCleanup {
+    /// URLs of the pending orders that were cleaned up.
+    pub orders: Vec<String>,
+
+    /// URLs of the authorizations that were deactivated.
+    pub authorizations: Vec<String>,
+
+    /// URLs of the expiring ready orders, which were left alone.
+    pub ready_orders: Vec<String>,
+
+    /// URLs of the orders that could not be (fully) cleaned up, with the errors.
+    pub failed: Vec<(String, eyre::Report)>,
+}
+
 /// Enumeration of reasons for revocation.
 ///
 /// The reason codes are taken from [RFC 5280 §5.3.1].
@@ -162,7 +638,12 @@ pub enum RevocationReason {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Directory, DirectoryUrl};
+    use std::{num::NonZeroUsize, time::Duration};
+
+    use pkcs8::EncodePrivateKey as _;
+
+    use super::*;
+    use crate::{order::CsrOrder, Certificate, Directory, DirectoryUrl};
 
     #[tokio::test]
     async fn test_create_order() {
@@ -178,4 +659,492 @@ mod tests {
 
         let _order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_generate_account_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let key_pem = generate_account_key(KeyKind::EcdsaP256).unwrap();
+        assert_eq!(KeyKind::of_pem(&key_pem).unwrap(), KeyKind::EcdsaP256);
+
+        let acc = dir.load_account(&key_pem, None).await.unwrap();
+        assert_eq!(acc.acme_private_key_pem().unwrap(), key_pem);
+    }
+
+    #[tokio::test]
+    async fn test_verify_server_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let pinned = acc.server_key_thumbprint().unwrap().unwrap();
+        acc.verify_server_key(&pinned).unwrap();
+
+        let other = acc.thumbprint().unwrap();
+        let err = acc.verify_server_key(&other).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::AccountKeyMismatch { expected, found: Some(found) })
+                if expected == &other && found == &pinned,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authorization_for_challenge() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let auths = order.authorizations().await.unwrap();
+        let challenge = auths[0].dns_challenge().unwrap();
+        let challenge_url = &challenge.api_challenge().url;
+
+        let auth = acc
+            .authorization_for_challenge(challenge_url)
+            .await
+            .unwrap();
+        assert_eq!(auth.api_auth(), auths[0].api_auth());
+        assert!(auth.challenge_by_url(challenge_url).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_orders() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let page = acc.list_orders_page(None).await.unwrap();
+        assert_eq!(page.orders.len(), 1);
+        let next = page.next.unwrap();
+        assert!(next.ends_with("/acme/acct/7728515/orders?cursor=2"));
+
+        let page = acc.list_orders_page(Some(&next)).await.unwrap();
+        assert_eq!(page.orders.len(), 1);
+        assert_eq!(page.next, None);
+
+        let orders = acc.list_all_orders().await.unwrap();
+        assert_eq!(orders.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_all_orders_cyclic() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/orders?cursor=2", "/orders?cursor=loop"))
+            .fetch()
+            .await
+            .unwrap();
+
+        let acc = dir.register_account(None).await.unwrap();
+
+        // the second page links to itself
+        let err = acc.list_all_orders().await.unwrap_err();
+        assert!(err.to_string().contains("already fetched"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_new_order_with_authorizations() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let (_order, auths) = acc
+            .new_order_with_authorizations("acme-test.example.com", &[])
+            .await
+            .unwrap();
+        assert_eq!(auths.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_pending_orders() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        // test orders expired in 2019
+        let cleanup = acc
+            .cleanup_pending_orders(Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(cleanup.orders.len(), 2);
+        assert_eq!(cleanup.authorizations.len(), 2);
+        assert!(cleanup.authorizations[0].ends_with("/acme/authz/YTqpYUthlVfwBncUufE8IRWLMSRqcSs"));
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_ready_orders() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| {
+                url.replace("/acme/order/", "/acme/order-ready/")
+                    .replace("/acme/authz/", "/acme/authz-valid/")
+            })
+            .fetch()
+            .await
+            .unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        // valid authorizations of ready orders may be reused, so they are left alone
+        let cleanup = acc
+            .cleanup_pending_orders(Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(cleanup.orders.is_empty());
+        assert!(cleanup.authorizations.is_empty());
+        assert_eq!(cleanup.ready_orders.len(), 2);
+        assert!(cleanup.failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_pending_orders_failed() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-missing/"))
+            .fetch()
+            .await
+            .unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        // each failed order is reported without stopping the cleanup
+        let cleanup = acc
+            .cleanup_pending_orders(Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(cleanup.orders.is_empty());
+        assert_eq!(cleanup.failed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_certificate() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let cert = crate::test::revocable_certificate();
+
+        acc.revoke_certificate(&cert, RevocationReason::KeyCompromise)
+            .await
+            .unwrap();
+
+        let other_cert = crate::test::self_signed_certificate("acme-test.example.com");
+        let err = acc
+            .revoke_certificate(&other_cert, RevocationReason::KeyCompromise)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Problem { problem, .. })
+                if problem.problem_type() == Some(api::ProblemType::Malformed),
+        ));
+
+        let req = revocation(&cert, RevocationReason::KeyCompromise).unwrap();
+        assert_eq!(req.reason, Some(1));
+        let req = revocation(&cert, RevocationReason::Unspecified).unwrap();
+        assert_eq!(req.reason, None);
+    }
+
+    #[tokio::test]
+    async fn test_change_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let new_key_pem = generate_account_key(KeyKind::EcdsaP256).unwrap();
+        acc.change_key(&new_key_pem).await.unwrap();
+
+        assert_eq!(acc.acme_private_key_pem().unwrap(), new_key_pem);
+
+        // existing orders use the new key
+        let auths = order.authorizations().await.unwrap();
+        assert_eq!(
+            auths[0].http_challenge().unwrap().http_proof().unwrap(),
+            format!(
+                "{}.{}",
+                auths[0].http_challenge().unwrap().http_token(),
+                acc.thumbprint().unwrap(),
+            ),
+        );
+
+        // key ID is preserved
+        let jws = acc
+            .sign_request("https://example.com", &api::EmptyObject, JwsFormat::Compact)
+            .await
+            .unwrap();
+        let protected = BASE64_URL_SAFE_NO_PAD
+            .decode(jws.split('.').next().unwrap())
+            .unwrap();
+        let protected = serde_json::from_slice::<serde_json::Value>(&protected).unwrap();
+        assert_eq!(
+            protected["kid"],
+            server.dir_url.replace("/directory", "/acme/acct/7728515"),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_from_url() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        // test order expired in 2019
+        let url = server
+            .dir_url
+            .replace("/directory", "/acme/order/YTqpYUthlVfwBncUufE8");
+        let Err(err) = acc.order_from_url(&url).await else {
+            panic!("order should have expired");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderExpired { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_order_from_url_resumable() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+
+        // pending order that expires in 2099
+        let url = server
+            .dir_url
+            .replace("/directory", "/acme/order-unexpired/YTqpYUthlVfwBncUufE8");
+        let ord = acc.order_from_url(&url).await.unwrap();
+        assert_eq!(ord.status(), Some(api::OrderStatus::Pending));
+        assert_eq!(ord.authorizations().await.unwrap().len(), 1);
+
+        // expiry is not checked once the order was finalized
+        let url = server
+            .dir_url
+            .replace("/directory", "/acme/order-stuck/YTqpYUthlVfwBncUufE8");
+        let ord = acc.order_from_url(&url).await.unwrap();
+        assert_eq!(ord.status(), Some(api::OrderStatus::Processing));
+    }
+
+    #[tokio::test]
+    async fn test_order_from_url_invalid() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+
+        let url = server
+            .dir_url
+            .replace("/directory", "/acme/order-invalid/YTqpYUthlVfwBncUufE8");
+        let Err(err) = acc.order_from_url(&url).await else {
+            panic!("order should be invalid");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderInvalid { url: order_url, .. }) if order_url == &url,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_renew_reusing_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let key_pem = crate::create_p256_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+        let key_pair = rcgen::KeyPair::from_pem(&key_pem).unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["acme-test.example.com".to_owned()])
+            .unwrap()
+            .self_signed(&key_pair)
+            .unwrap();
+        let previous = Certificate::parse(key_pem, cert.pem()).unwrap();
+
+        let order = acc.renew(&previous).await.unwrap();
+        assert_eq!(order.api_order().domains(), ["acme-test.example.com"]);
+
+        // shortcut auth
+        let csr = CsrOrder { order: order.order };
+        let renewed = csr
+            .finalize_renewal(&previous, Duration::from_millis(1))
+            .await
+            .unwrap()
+            .download_cert()
+            .await
+            .unwrap();
+
+        assert_eq!(renewed.private_key(), previous.private_key());
+    }
+
+    #[tokio::test]
+    async fn test_new_order_with_idempotency_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-order", "/acme/new-order-idempotent"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+
+        acc.new_order_with_idempotency_key(
+            "acme-test.example.com",
+            &[],
+            crate::test::IDEMPOTENCY_KEY,
+        )
+        .await
+        .unwrap();
+
+        // the mock server rejects order creation without the header
+        assert!(acc.new_order("acme-test.example.com", &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_new_order_without_idempotency_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+
+        // the mock server rejects order creation with the header
+        acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        assert!(acc
+            .new_order_with_idempotency_key(
+                "acme-test.example.com",
+                &[],
+                &util::generate_idempotency_key(),
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_orders() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .max_concurrent_orders(NonZeroUsize::MIN)
+            .fetch()
+            .await
+            .unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let blocked = acc.new_order("acme-test.example.com", &[]);
+        let blocked = tokio::time::timeout(Duration::from_millis(50), blocked).await;
+        assert!(blocked.is_err(), "second order should wait for the first");
+
+        drop(order);
+        let _order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dns_proof_for_token() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let order = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let auths = order.authorizations().await.unwrap();
+        let challenge = auths[0].dns_challenge().unwrap();
+
+        assert_eq!(
+            acc.dns_proof_for_token(&challenge.api_challenge().token)
+                .unwrap(),
+            challenge.dns_proof().unwrap(),
+        );
+
+        let challenge = auths[0].http_challenge().unwrap();
+        assert_eq!(
+            acc.key_authorization(challenge.http_token()).unwrap(),
+            challenge.http_proof().unwrap(),
+        );
+    }
 }
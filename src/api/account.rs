@@ -41,6 +41,32 @@ pub struct Account {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub orders: Option<String>,
+
+    /// The account's public key as a JWK, if the ACME API includes it in responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<serde_json::Value>,
+}
+
+/// A page of an account's orders list.
+///
+/// See [RFC 8555 §7.1.2.1].
+///
+/// # Example JSON
+///
+/// ```json
+/// {
+///   "orders": [
+///     "https://example.com/acme/order/TOlocE8rfgo",
+///     "https://example.com/acme/order/4E16bbL5iSw"
+///   ]
+/// }
+/// ```
+///
+/// [RFC 8555 §7.1.2.1]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.1.2.1
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrdersList {
+    /// URLs of the orders.
+    pub orders: Vec<String>,
 }
 
 impl Account {
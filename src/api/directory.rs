@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 /// Directory object for ACME client self-configuration.
@@ -78,10 +80,77 @@ pub struct DirectoryMeta {
     /// `externalAccountBinding` field associating the new account with an external account.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub external_account_required: Option<bool>,
+
+    /// Certificate profiles offered by the ACME server.
+    ///
+    /// See the [ACME profiles draft].
+    ///
+    /// [ACME profiles draft]: https://datatracker.ietf.org/doc/draft-aaron-acme-profiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<DirectoryProfiles>,
 }
 
 impl DirectoryMeta {
     pub fn external_account_required(&self) -> bool {
         self.external_account_required.unwrap_or(false)
     }
+
+    /// Returns a map of offered profile names to their descriptions.
+    ///
+    /// Profiles advertised without descriptions map to an empty string.
+    pub fn profile_descriptions(&self) -> HashMap<String, String> {
+        match &self.profiles {
+            None => HashMap::new(),
+            Some(DirectoryProfiles::Names(names)) => names
+                .iter()
+                .map(|name| (name.clone(), String::new()))
+                .collect(),
+            Some(DirectoryProfiles::Descriptions(descriptions)) => descriptions.clone(),
+        }
+    }
+}
+
+/// Certificate profiles offered by the ACME server.
+///
+/// ACME servers advertise profiles either as an object mapping names to descriptions, as in the
+/// ACME profiles draft, or as a plain array of names.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DirectoryProfiles {
+    /// Profile names, without descriptions.
+    Names(Vec<String>),
+
+    /// Profile names mapped to their descriptions.
+    Descriptions(HashMap<String, String>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_descriptions() {
+        let meta = serde_json::from_str::<DirectoryMeta>(
+            r#"{ "profiles": { "classic": "The same profile you're accustomed to" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            meta.profile_descriptions(),
+            HashMap::from([(
+                "classic".to_owned(),
+                "The same profile you're accustomed to".to_owned(),
+            )]),
+        );
+
+        let meta =
+            serde_json::from_str::<DirectoryMeta>(r#"{ "profiles": ["classic", "shortlived"] }"#)
+                .unwrap();
+        assert_eq!(
+            meta.profile_descriptions(),
+            HashMap::from([
+                ("classic".to_owned(), String::new()),
+                ("shortlived".to_owned(), String::new()),
+            ]),
+        );
+    }
 }
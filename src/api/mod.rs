@@ -19,10 +19,10 @@ mod order;
 mod revocation;
 
 pub use self::{
-    account::Account,
+    account::{Account, OrdersList},
     authorization::{Authorization, AuthorizationStatus},
     challenge::{Challenge, ChallengeStatus},
-    directory::{Directory, DirectoryMeta},
+    directory::{Directory, DirectoryMeta, DirectoryProfiles},
     finalize::Finalize,
     identifier::Identifier,
     order::{Order, OrderStatus},
@@ -55,14 +55,53 @@ pub struct Problem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detail: Option<String>,
 
+    /// HTTP status code of the response this problem was returned in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subproblems: Option<Vec<Subproblem>>,
+
+    /// Short, human-readable summary of the problem type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// URI identifying this specific occurrence of the problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+
+    /// Signature algorithms accepted by the ACME API, for `badSignatureAlgorithm` problems.
+    ///
+    /// See [RFC 8555 §6.2].
+    ///
+    /// [RFC 8555 §6.2]: https://datatracker.ietf.org/doc/html/rfc8555#section-6.2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithms: Option<Vec<String>>,
+
+    /// Any other fields of the problem document.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Problem {
+    /// Returns the problem type, if it is one of the types defined by ACME.
+    pub fn problem_type(&self) -> Option<ProblemType> {
+        ProblemType::from_urn(&self._type)
+    }
+
     /// Returns true if problem type is "badNonce".
     pub fn is_bad_nonce(&self) -> bool {
-        self._type == "badNonce"
+        self.problem_type() == Some(ProblemType::BadNonce)
+    }
+
+    /// Returns true if problem type is "rateLimited" or the HTTP status is `429 Too Many Requests`.
+    pub fn is_rate_limited(&self) -> bool {
+        self.problem_type() == Some(ProblemType::RateLimited) || self.status == Some(429)
+    }
+
+    /// Returns true if problem type is "accountDoesNotExist".
+    pub fn is_account_does_not_exist(&self) -> bool {
+        self.problem_type() == Some(ProblemType::AccountDoesNotExist)
     }
 
     /// Returns true if problem details indicate that JWS verification failed.
@@ -93,6 +132,137 @@ pub struct Subproblem {
     pub identifier: Option<identifier::Identifier>,
 }
 
+impl Subproblem {
+    /// Returns the problem type, if it is one of the types defined by ACME.
+    pub fn problem_type(&self) -> Option<ProblemType> {
+        ProblemType::from_urn(&self._type)
+    }
+}
+
+impl fmt::Display for Subproblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(identifier) = &self.identifier {
+            write!(f, "{}: ", identifier.value)?;
+        }
+
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {detail}", self._type),
+            _ => write!(f, "{}", self._type),
+        }
+    }
+}
+
+/// Problem types defined by ACME, i.e., `urn:ietf:params:acme:error:*`.
+///
+/// See [RFC 8555 §6.7].
+///
+/// [RFC 8555 §6.7]: https://datatracker.ietf.org/doc/html/rfc8555#section-6.7
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProblemType {
+    AccountDoesNotExist,
+    AlreadyRevoked,
+    BadCsr,
+    BadNonce,
+    BadPublicKey,
+    BadRevocationReason,
+    BadSignatureAlgorithm,
+    Caa,
+    Compound,
+    Connection,
+    Dns,
+    ExternalAccountRequired,
+    IncorrectResponse,
+    InvalidContact,
+    Malformed,
+    OrderNotReady,
+    RateLimited,
+    RejectedIdentifier,
+    ServerInternal,
+    Tls,
+    Unauthorized,
+    UnsupportedContact,
+    UnsupportedIdentifier,
+    UserActionRequired,
+}
+
+impl ProblemType {
+    const ALL: [ProblemType; 24] = [
+        ProblemType::AccountDoesNotExist,
+        ProblemType::AlreadyRevoked,
+        ProblemType::BadCsr,
+        ProblemType::BadNonce,
+        ProblemType::BadPublicKey,
+        ProblemType::BadRevocationReason,
+        ProblemType::BadSignatureAlgorithm,
+        ProblemType::Caa,
+        ProblemType::Compound,
+        ProblemType::Connection,
+        ProblemType::Dns,
+        ProblemType::ExternalAccountRequired,
+        ProblemType::IncorrectResponse,
+        ProblemType::InvalidContact,
+        ProblemType::Malformed,
+        ProblemType::OrderNotReady,
+        ProblemType::RateLimited,
+        ProblemType::RejectedIdentifier,
+        ProblemType::ServerInternal,
+        ProblemType::Tls,
+        ProblemType::Unauthorized,
+        ProblemType::UnsupportedContact,
+        ProblemType::UnsupportedIdentifier,
+        ProblemType::UserActionRequired,
+    ];
+
+    /// Returns the problem type identified by `urn`, e.g.,
+    /// `"urn:ietf:params:acme:error:rateLimited"`.
+    ///
+    /// The `urn:acme:error:` prefix used by pre-RFC ACME API providers is also accepted.
+    pub fn from_urn(urn: &str) -> Option<Self> {
+        let name = urn
+            .strip_prefix("urn:ietf:params:acme:error:")
+            .or_else(|| urn.strip_prefix("urn:acme:error:"))?;
+
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+
+    /// Returns the name of the problem type, e.g., `"rateLimited"`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ProblemType::AccountDoesNotExist => "accountDoesNotExist",
+            ProblemType::AlreadyRevoked => "alreadyRevoked",
+            ProblemType::BadCsr => "badCSR",
+            ProblemType::BadNonce => "badNonce",
+            ProblemType::BadPublicKey => "badPublicKey",
+            ProblemType::BadRevocationReason => "badRevocationReason",
+            ProblemType::BadSignatureAlgorithm => "badSignatureAlgorithm",
+            ProblemType::Caa => "caa",
+            ProblemType::Compound => "compound",
+            ProblemType::Connection => "connection",
+            ProblemType::Dns => "dns",
+            ProblemType::ExternalAccountRequired => "externalAccountRequired",
+            ProblemType::IncorrectResponse => "incorrectResponse",
+            ProblemType::InvalidContact => "invalidContact",
+            ProblemType::Malformed => "malformed",
+            ProblemType::OrderNotReady => "orderNotReady",
+            ProblemType::RateLimited => "rateLimited",
+            ProblemType::RejectedIdentifier => "rejectedIdentifier",
+            ProblemType::ServerInternal => "serverInternal",
+            ProblemType::Tls => "tls",
+            ProblemType::Unauthorized => "unauthorized",
+            ProblemType::UnsupportedContact => "unsupportedContact",
+            ProblemType::UnsupportedIdentifier => "unsupportedIdentifier",
+            ProblemType::UserActionRequired => "userActionRequired",
+        }
+    }
+}
+
+impl fmt::Display for ProblemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,6 +273,67 @@ mod tests {
         assert_eq!("\"\"", x);
     }
 
+    #[test]
+    fn test_problem_fields() {
+        let problem = serde_json::from_str::<Problem>(
+            r#"{
+                "type": "urn:ietf:params:acme:error:badSignatureAlgorithm",
+                "detail": "Unsupported algorithm",
+                "instance": "https://example.com/acme/problem/1",
+                "algorithms": ["ES256", "EdDSA"],
+                "vendorField": 42
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            problem.instance.as_deref(),
+            Some("https://example.com/acme/problem/1"),
+        );
+        assert_eq!(problem.algorithms.unwrap(), ["ES256", "EdDSA"]);
+        assert_eq!(problem.extra["vendorField"], 42);
+        assert!(!problem.extra.contains_key("instance"));
+    }
+
+    #[test]
+    fn test_problem_type() {
+        let problem = Problem {
+            _type: "urn:ietf:params:acme:error:rateLimited".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(problem.problem_type(), Some(ProblemType::RateLimited));
+
+        assert_eq!(
+            ProblemType::from_urn("urn:acme:error:badCSR"),
+            Some(ProblemType::BadCsr),
+        );
+        assert_eq!(
+            ProblemType::from_urn("urn:ietf:params:acme:error:unknown"),
+            None
+        );
+        assert_eq!(ProblemType::from_urn("rateLimited"), None);
+    }
+
+    #[test]
+    fn test_is_account_does_not_exist() {
+        for _type in [
+            "urn:ietf:params:acme:error:accountDoesNotExist",
+            "urn:acme:error:accountDoesNotExist",
+        ] {
+            let problem = Problem {
+                _type: _type.to_owned(),
+                ..Default::default()
+            };
+            assert!(problem.is_account_does_not_exist(), "{_type}");
+        }
+
+        let problem = Problem {
+            _type: "urn:ietf:params:acme:error:malformed".to_owned(),
+            ..Default::default()
+        };
+        assert!(!problem.is_account_does_not_exist());
+    }
+
     #[test]
     fn test_api_empty_object() {
         let x = serde_json::to_string(&EmptyObject).unwrap();
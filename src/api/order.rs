@@ -1,4 +1,7 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
 use crate::api;
 
@@ -87,6 +90,31 @@ impl Order {
             .collect()
     }
 
+    /// Returns the time after which the ACME API provider considers this order invalid.
+    ///
+    /// Returns `None` if `expires` is not set.
+    pub fn expires_at(&self) -> eyre::Result<Option<OffsetDateTime>> {
+        self.expires
+            .as_deref()
+            .map(|expires| OffsetDateTime::parse(expires, &Rfc3339))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Returns the certificate validity period requested by this order (`notAfter - notBefore`).
+    ///
+    /// Returns `None` if either `notBefore` or `notAfter` are not set.
+    pub fn requested_lifetime(&self) -> eyre::Result<Option<Duration>> {
+        let (Some(not_before), Some(not_after)) = (&self.not_before, &self.not_after) else {
+            return Ok(None);
+        };
+
+        let not_before = OffsetDateTime::parse(not_before, &Rfc3339)?;
+        let not_after = OffsetDateTime::parse(not_after, &Rfc3339)?;
+
+        Ok(Some((not_after - not_before).try_into()?))
+    }
+
     /// Let's Encrypt was observed to return domains in alternate order which may flip primary with
     /// SAN(s).
     ///
@@ -0,0 +1,235 @@
+use std::{future::Future, pin::Pin};
+
+/// Future returned by [`CaaResolver::lookup_caa()`].
+pub type CaaLookupFuture<'a> =
+    Pin<Box<dyn Future<Output = eyre::Result<Vec<CaaRecord>>> + Send + 'a>>;
+
+/// Looks up `CAA` records for [`precheck_caa()`].
+pub trait CaaResolver: Send + Sync {
+    /// Returns all `CAA` records named `name`.
+    ///
+    /// The name does not have a trailing dot, e.g. `example.org`. Returns an empty list if no such
+    /// records exist. Aliases (`CNAME` records) are expected to be followed, as described in
+    /// [RFC 8659 §3].
+    ///
+    /// [RFC 8659 §3]: https://datatracker.ietf.org/doc/html/rfc8659#section-3
+    fn lookup_caa<'a>(&'a self, name: &'a str) -> CaaLookupFuture<'a>;
+}
+
+/// A `CAA` resource record, as described in [RFC 8659 §4.1].
+///
+/// [RFC 8659 §4.1]: https://datatracker.ietf.org/doc/html/rfc8659#section-4.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaaRecord {
+    /// Flags; only the Issuer Critical flag (`128`) is defined.
+    pub flags: u8,
+
+    /// Property tag, e.g., `issue`.
+    pub tag: String,
+
+    /// Property value, e.g., `letsencrypt.org`.
+    pub value: String,
+}
+
+impl CaaRecord {
+    /// Constructs new `CAA` record.
+    pub fn new(flags: u8, tag: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            flags,
+            tag: tag.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Returns true if the Issuer Critical flag is set.
+    pub fn is_critical(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    /// Returns the issuer domain name of an `issue` or `issuewild` property value, if any.
+    ///
+    /// E.g., `letsencrypt.org` for `letsencrypt.org; validationmethods=dns-01`, and `None` for
+    /// `;`, which forbids issuance.
+    fn issuer_domain_name(&self) -> Option<&str> {
+        let name = self.value.split(';').next().unwrap_or_default().trim();
+        (!name.is_empty()).then_some(name)
+    }
+}
+
+/// Property tags understood by [`precheck_caa()`].
+const KNOWN_TAGS: &[&str] = &["issue", "issuewild", "iodef"];
+
+/// Checks whether the ACME API provider identified by `caa_identity` may issue certificates for
+/// `domain` according to its `CAA` records, looked up using `resolver`.
+///
+/// Useful for failing fast before creating an order, since validation fails late with a `caa`
+/// problem otherwise. `caa_identity` is one of the provider's
+/// [CAA identities](crate::Directory::caa_identities), e.g., `letsencrypt.org`. Wildcard domains
+/// are given with their `*.` prefix.
+///
+/// Follows [RFC 8659 §3]: the relevant records are the first non-empty set found when climbing
+/// from `domain` towards the root. Issuance is permitted if there is no such set or it contains
+/// no relevant properties; otherwise, a relevant property must name `caa_identity`. For wildcard
+/// domains, `issuewild` properties take precedence over `issue` properties. Critical properties
+/// with unknown tags forbid issuance.
+///
+/// [RFC 8659 §3]: https://datatracker.ietf.org/doc/html/rfc8659#section-3
+pub async fn precheck_caa(
+    domain: &str,
+    caa_identity: &str,
+    resolver: &impl CaaResolver,
+) -> eyre::Result<bool> {
+    let (wildcard, domain) = match domain.strip_prefix("*.") {
+        Some(domain) => (true, domain),
+        None => (false, domain),
+    };
+
+    let mut name = domain.trim_end_matches('.');
+
+    let records = loop {
+        let records = resolver.lookup_caa(name).await?;
+
+        if !records.is_empty() {
+            break records;
+        }
+
+        match name.split_once('.') {
+            Some((_, parent)) if !parent.is_empty() => name = parent,
+            _ => return Ok(true),
+        }
+    };
+
+    let unknown_critical = records.iter().any(|record| {
+        record.is_critical()
+            && !KNOWN_TAGS
+                .iter()
+                .any(|tag| record.tag.eq_ignore_ascii_case(tag))
+    });
+
+    if unknown_critical {
+        return Ok(false);
+    }
+
+    let with_tag = |tag: &str| -> Vec<&CaaRecord> {
+        records
+            .iter()
+            .filter(|record| record.tag.eq_ignore_ascii_case(tag))
+            .collect()
+    };
+
+    let issuewild = with_tag("issuewild");
+    let relevant = if wildcard && !issuewild.is_empty() {
+        issuewild
+    } else {
+        with_tag("issue")
+    };
+
+    if relevant.is_empty() {
+        return Ok(true);
+    }
+
+    Ok(relevant.iter().any(|record| {
+        record
+            .issuer_domain_name()
+            .is_some_and(|name| name.eq_ignore_ascii_case(caa_identity))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    struct StaticResolver(HashMap<&'static str, Vec<CaaRecord>>);
+
+    impl CaaResolver for StaticResolver {
+        fn lookup_caa<'a>(&'a self, name: &'a str) -> CaaLookupFuture<'a> {
+            Box::pin(async move { Ok(self.0.get(name).cloned().unwrap_or_default()) })
+        }
+    }
+
+    fn resolver(records: &[(&'static str, CaaRecord)]) -> StaticResolver {
+        let mut map = HashMap::<_, Vec<_>>::new();
+
+        for (name, record) in records {
+            map.entry(*name).or_default().push(record.clone());
+        }
+
+        StaticResolver(map)
+    }
+
+    async fn permitted(domain: &str, caa_identity: &str, resolver: &StaticResolver) -> bool {
+        precheck_caa(domain, caa_identity, resolver).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_precheck_caa_climbs_tree() {
+        let res = resolver(&[]);
+        assert!(permitted("a.b.example.org", "ca.test", &res).await);
+
+        let res = resolver(&[("example.org", CaaRecord::new(0, "issue", "ca.test"))]);
+        assert!(permitted("a.b.example.org", "ca.test", &res).await);
+        assert!(!permitted("a.b.example.org", "other.test", &res).await);
+
+        // the closest set of records is relevant
+        let res = resolver(&[
+            ("b.example.org", CaaRecord::new(0, "issue", "other.test")),
+            ("example.org", CaaRecord::new(0, "issue", "ca.test")),
+        ]);
+        assert!(!permitted("a.b.example.org", "ca.test", &res).await);
+    }
+
+    #[tokio::test]
+    async fn test_precheck_caa_issue_values() {
+        let res = resolver(&[
+            ("example.org", CaaRecord::new(0, "issue", "other.test")),
+            (
+                "example.org",
+                CaaRecord::new(0, "issue", " CA.test ; validationmethods=dns-01"),
+            ),
+        ]);
+        assert!(permitted("example.org", "ca.test", &res).await);
+
+        let res = resolver(&[("example.org", CaaRecord::new(0, "issue", ";"))]);
+        assert!(!permitted("example.org", "ca.test", &res).await);
+
+        let res = resolver(&[("example.org", CaaRecord::new(0, "iodef", "mailto:a@b.test"))]);
+        assert!(permitted("example.org", "ca.test", &res).await);
+    }
+
+    #[tokio::test]
+    async fn test_precheck_caa_issuewild() {
+        let res = resolver(&[
+            ("example.org", CaaRecord::new(0, "issue", "ca.test")),
+            ("example.org", CaaRecord::new(0, "issuewild", ";")),
+        ]);
+        assert!(permitted("example.org", "ca.test", &res).await);
+        assert!(!permitted("*.example.org", "ca.test", &res).await);
+
+        // falls back to issue properties
+        let res = resolver(&[("example.org", CaaRecord::new(0, "issue", "ca.test"))]);
+        assert!(permitted("*.example.org", "ca.test", &res).await);
+    }
+
+    #[tokio::test]
+    async fn test_precheck_caa_critical() {
+        let res = resolver(&[
+            ("example.org", CaaRecord::new(0, "issue", "ca.test")),
+            ("example.org", CaaRecord::new(0, "future", "value")),
+        ]);
+        assert!(permitted("example.org", "ca.test", &res).await);
+
+        let res = resolver(&[
+            ("example.org", CaaRecord::new(0, "issue", "ca.test")),
+            ("example.org", CaaRecord::new(128, "future", "value")),
+        ]);
+        assert!(!permitted("example.org", "ca.test", &res).await);
+        // tags are case-insensitive, so critical known tags do not forbid issuance
+        let res = resolver(&[
+            ("example.org", CaaRecord::new(128, "ISSUE", "ca.test")),
+            ("example.org", CaaRecord::new(128, "Issue", "other.test")),
+        ]);
+        assert!(permitted("example.org", "ca.test", &res).await);
+    }
+}
@@ -1,52 +1,408 @@
-use std::io::{BufReader, Cursor};
+use std::{
+    io::{BufReader, Cursor},
+    net::IpAddr,
+    time::Duration,
+};
 
 use der::{
-    asn1::Ia5String,
+    asn1::{Any, Ia5String, OctetString, Utf8StringRef},
+    oid::{
+        db::{rfc4519, rfc5280::ID_KP_SERVER_AUTH},
+        AssociatedOid, ObjectIdentifier,
+    },
     time::{OffsetDateTime, PrimitiveDateTime},
-    Decode as _, DecodePem as _,
+    Decode as _, DecodePem as _, EncodePem as _,
 };
 use eyre::{eyre, WrapErr as _};
-use pkcs8::{DecodePrivateKey, EncodePrivateKey};
+use pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey as _};
+use rand::RngCore as _;
 use x509_cert::{
-    builder::{Builder, RequestBuilder as CsrBuilder},
-    ext::pkix::{name::GeneralName, SubjectAltName},
-    name::Name,
+    attr::AttributeTypeAndValue,
+    builder::{Builder, CertificateBuilder, Profile, RequestBuilder as CsrBuilder},
+    ext::{
+        pkix::{name::GeneralName, ExtendedKeyUsage, SubjectAltName},
+        AsExtension, Extension,
+    },
+    name::{Name, RdnSequence, RelativeDistinguishedName},
+    serial_number::SerialNumber,
+    spki::SubjectPublicKeyInfoOwned,
+    time::Validity,
 };
 use zeroize::Zeroizing;
 
+use crate::{api, util, Error};
+
 /// Make a P-256 private key (from which we can derive a public key).
 pub fn create_p256_key() -> p256::ecdsa::SigningKey {
-    let csprng = &mut rand::thread_rng();
-    ecdsa::SigningKey::from(p256::SecretKey::random(csprng))
+    create_p256_key_with_rng(&mut rand::thread_rng())
+}
+
+/// Make a P-256 private key using the random number generator `rng`.
+///
+/// Useful for generating reproducible keys in tests or for using an approved DRBG in regulated
+/// environments.
+///
+/// # Security
+///
+/// The private key is only as unpredictable as `rng`. A seeded or otherwise deterministic RNG will
+/// always generate the same key, so never use one outside of tests. Prefer [`create_p256_key()`],
+/// which uses the operating system's RNG, unless you have a specific reason not to.
+pub fn create_p256_key_with_rng(
+    rng: &mut (impl rand::CryptoRng + rand::RngCore),
+) -> p256::ecdsa::SigningKey {
+    ecdsa::SigningKey::from(p256::SecretKey::random(rng))
+}
+
+/// Make a P-256 private key whose scalar is the big-endian integer `seed`.
+///
+/// Useful for generating stable keys, and so stable JWK thumbprints, in tests or for deriving
+/// keys from a master secret, e.g., using HKDF. Fails with [`Error::InvalidKeySeed`] if `seed` is
+/// zero or not less than the order of the P-256 curve; the chance of a uniformly random seed being
+/// rejected is negligible.
+///
+/// # Security
+///
+/// The private key is exactly as secret as `seed`. Never use a constant seed outside of tests.
+pub fn create_p256_key_from_seed(seed: &[u8; 32]) -> eyre::Result<p256::ecdsa::SigningKey> {
+    p256::ecdsa::SigningKey::from_bytes(&(*seed).into()).map_err(|_| Error::InvalidKeySeed.into())
+}
+
+/// Make a P-384 private key (from which we can derive a public key).
+///
+/// Can be used as an account key, e.g., when P-384 is required for compliance reasons; see
+/// [`KeyKind::EcdsaP384`](crate::KeyKind::EcdsaP384).
+pub fn create_p384_key() -> p384::ecdsa::SigningKey {
+    ecdsa::SigningKey::from(p384::SecretKey::random(&mut rand::thread_rng()))
+}
+
+/// Subject attributes of the CSR submitted when finalizing an order.
+///
+/// The Subject Alternative Name (SAN) extension always contains the domains of the order. See
+/// [`CsrOrder::finalize_with_csr_params()`].
+///
+/// [`CsrOrder::finalize_with_csr_params()`]: crate::order::CsrOrder::finalize_with_csr_params()
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsrParams {
+    common_name: Option<String>,
+    organization: Option<String>,
+    organizational_unit: Option<String>,
+    must_staple: bool,
+}
+
+impl CsrParams {
+    /// Constructs new CSR parameters which use the first domain of the order as the Common Name
+    /// (CN) and no other subject attributes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the subject's Common Name (CN).
+    pub fn with_common_name(mut self, common_name: impl Into<String>) -> Self {
+        self.common_name = Some(common_name.into());
+        self
+    }
+
+    /// Sets the subject's Organization (O).
+    pub fn with_organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Sets the subject's Organizational Unit (OU).
+    pub fn with_organizational_unit(mut self, organizational_unit: impl Into<String>) -> Self {
+        self.organizational_unit = Some(organizational_unit.into());
+        self
+    }
+
+    /// Sets whether to request the OCSP Must-Staple TLS feature.
+    ///
+    /// Adds the TLS Feature extension with `status_request`, as defined in [RFC 7633], to the CSR.
+    /// ACME API providers may ignore it.
+    ///
+    /// [RFC 7633]: https://datatracker.ietf.org/doc/html/rfc7633
+    pub fn with_must_staple(mut self, must_staple: bool) -> Self {
+        self.must_staple = must_staple;
+        self
+    }
+
+    /// Returns the subject's Common Name (CN), if set.
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    /// Returns the subject's Organization (O), if set.
+    pub fn organization(&self) -> Option<&str> {
+        self.organization.as_deref()
+    }
+
+    /// Returns the subject's Organizational Unit (OU), if set.
+    pub fn organizational_unit(&self) -> Option<&str> {
+        self.organizational_unit.as_deref()
+    }
+
+    /// Returns true if the OCSP Must-Staple TLS feature is requested.
+    pub fn must_staple(&self) -> bool {
+        self.must_staple
+    }
+
+    /// Returns the subject name, using `primary_domain` as the Common Name (CN) if none is set.
+    fn subject(&self, primary_domain: &str) -> eyre::Result<Name> {
+        let attributes = [
+            (rfc4519::O, self.organization.as_deref()),
+            (rfc4519::OU, self.organizational_unit.as_deref()),
+            (
+                rfc4519::CN,
+                Some(self.common_name.as_deref().unwrap_or(primary_domain)),
+            ),
+        ];
+
+        let rdns = attributes
+            .into_iter()
+            .filter_map(|(oid, value)| Some((oid, value?)))
+            .map(|(oid, value)| {
+                let value = Any::encode_from(&Utf8StringRef::new(value)?)?;
+                let rdn = RelativeDistinguishedName::try_from(vec![AttributeTypeAndValue {
+                    oid,
+                    value,
+                }])?;
+                Ok(rdn)
+            })
+            .collect::<der::Result<_>>()?;
+
+        Ok(RdnSequence(rdns))
+    }
 }
 
 /// Creates a CSR with `domains` and signs it with `signer`.
 ///
-/// The first item of `domains` is picked for the CSR's Common Name (CN). All domains are added to a
-/// Subject Alternative Name (SAN) extension.
+/// The subject is taken from `params`, defaulting the Common Name (CN) to the first item of
+/// `domains`. All domains are added to a Subject Alternative Name (SAN) extension, unless the
+/// CSR is for a single domain that is also the CN. Internationalized domain names are
+/// converted to their ASCII form.
 pub(crate) fn create_csr(
     signer: &p256::ecdsa::SigningKey,
     domains: &[&str],
+    params: &CsrParams,
 ) -> eyre::Result<x509_cert::request::CertReq> {
+    let domains = domains
+        .iter()
+        .map(|domain| util::domain_to_ascii(domain))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
     let primary_domain = domains.first().unwrap();
-    let subject = format!("CN={primary_domain}").parse::<Name>().unwrap();
+    let subject = params.subject(primary_domain)?;
 
     let mut csr = CsrBuilder::new(subject, signer).unwrap();
 
-    if domains.len() > 1 {
+    let cn_is_only_domain = domains.len() == 1
+        && params
+            .common_name()
+            .map_or(true, |cn| cn == *primary_domain);
+
+    if !cn_is_only_domain {
         csr.add_extension(&SubjectAltName(
-            domains[0..]
+            domains
                 .iter()
-                .map(|domain| GeneralName::DnsName(Ia5String::new(domain).unwrap()))
-                .collect(),
+                .map(|domain| Ok(GeneralName::DnsName(Ia5String::new(domain.as_ref())?)))
+                .collect::<eyre::Result<_>>()?,
         ))
         .unwrap();
     }
 
+    if params.must_staple {
+        csr.add_extension(&TlsFeature(vec![TLS_FEATURE_STATUS_REQUEST]))?;
+    }
+
     csr.build::<p256::ecdsa::DerSignature>()
         .context("build csr")
 }
 
+/// Checks that the identifiers requested by the DER-encoded CSR `csr_der` are exactly
+/// `identifiers`.
+///
+/// The requested identifiers are the DNS names and IP addresses in the CSR's Subject Alternative
+/// Name extension and its Common Name (CN), if any. DNS names are compared case-insensitively.
+/// Fails with [`Error::CsrIdentifiersMismatch`] otherwise.
+pub(crate) fn check_csr_identifiers(
+    csr_der: &[u8],
+    identifiers: &[api::Identifier],
+) -> eyre::Result<()> {
+    let csr = x509_cert::request::CertReq::from_der(csr_der).context("parse csr")?;
+
+    let mut requested = Vec::new();
+
+    for atv in csr.info.subject.0.iter().flat_map(|rdn| rdn.0.iter()) {
+        if atv.oid == rfc4519::CN {
+            let cn = util::decode_directory_string(&atv.value)?;
+            requested.push(normalize_identifier(&cn));
+        }
+    }
+
+    let extensions = csr
+        .info
+        .attributes
+        .iter()
+        .flat_map(|attr| attr.values.iter())
+        .filter_map(|value| value.decode_as::<Vec<Extension>>().ok())
+        .flatten();
+
+    for ext in extensions.filter(|ext| ext.extn_id == SubjectAltName::OID) {
+        for name in SubjectAltName::from_der(ext.extn_value.as_bytes())?.0 {
+            match name {
+                GeneralName::DnsName(dns) => requested.push(normalize_identifier(dns.as_str())),
+                GeneralName::IpAddress(ip) => {
+                    let ip = match *ip.as_bytes() {
+                        [a, b, c, d] => IpAddr::from([a, b, c, d]),
+                        _ => IpAddr::from(<[u8; 16]>::try_from(ip.as_bytes())?),
+                    };
+                    requested.push(ip.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let expected = identifiers
+        .iter()
+        .map(|id| normalize_identifier(&id.value))
+        .collect::<Vec<_>>();
+
+    let missing = expected
+        .iter()
+        .filter(|id| !requested.contains(id))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut unexpected = requested
+        .iter()
+        .filter(|id| !expected.contains(id))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() && unexpected.is_empty() {
+        return Ok(());
+    }
+
+    unexpected.sort();
+    unexpected.dedup();
+
+    Err(Error::CsrIdentifiersMismatch {
+        missing,
+        unexpected,
+    }
+    .into())
+}
+
+/// Normalizes a DNS name or IP address for comparison.
+fn normalize_identifier(value: &str) -> String {
+    match value.parse::<IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => value.to_ascii_lowercase(),
+    }
+}
+
+/// The `status_request` TLS extension type, as requested by OCSP Must-Staple certificates.
+const TLS_FEATURE_STATUS_REQUEST: u16 = 5;
+
+/// The TLS Feature extension, as defined in [RFC 7633 §6].
+///
+/// [RFC 7633 §6]: https://datatracker.ietf.org/doc/html/rfc7633#section-6
+struct TlsFeature(Vec<u16>);
+
+impl AssociatedOid for TlsFeature {
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.24");
+}
+
+impl der::Encode for TlsFeature {
+    fn encoded_len(&self) -> der::Result<der::Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, encoder: &mut impl der::Writer) -> der::Result<()> {
+        self.0.encode(encoder)
+    }
+}
+
+impl AsExtension for TlsFeature {
+    fn critical(&self, _subject: &Name, _extensions: &[Extension]) -> bool {
+        false
+    }
+}
+
+/// Validity period of TLS-ALPN-01 validation certificates.
+const TLS_ALPN_CERTIFICATE_VALIDITY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The `id-pe-acmeIdentifier` extension, as defined in [RFC 8737 §3].
+///
+/// [RFC 8737 §3]: https://datatracker.ietf.org/doc/html/rfc8737#section-3
+struct AcmeIdentifier(OctetString);
+
+impl AssociatedOid for AcmeIdentifier {
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.31");
+}
+
+impl der::Encode for AcmeIdentifier {
+    fn encoded_len(&self) -> der::Result<der::Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, encoder: &mut impl der::Writer) -> der::Result<()> {
+        self.0.encode(encoder)
+    }
+}
+
+impl AsExtension for AcmeIdentifier {
+    fn critical(&self, _subject: &Name, _extensions: &[Extension]) -> bool {
+        true
+    }
+}
+
+/// Creates a self-signed TLS-ALPN-01 validation certificate for `domain` with a new P-256 key.
+///
+/// The certificate has a single DNS name SAN and the critical `id-pe-acmeIdentifier` extension
+/// containing `proof`. Returns the PEM-encoded certificate and private key.
+pub(crate) fn create_tls_alpn_certificate(
+    domain: &str,
+    proof: [u8; 32],
+) -> eyre::Result<(String, Zeroizing<String>)> {
+    let private_key = create_p256_key();
+    let public_key = private_key.verifying_key().to_public_key_der()?;
+    let spki = SubjectPublicKeyInfoOwned::from_der(public_key.as_bytes())?;
+
+    // serial numbers must be positive and at most 20 octets
+    let mut serial = [0; 16];
+    rand::thread_rng().fill_bytes(&mut serial);
+    serial[0] &= 0x7f;
+
+    let mut cert = CertificateBuilder::new(
+        Profile::Leaf {
+            issuer: Name::default(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        },
+        SerialNumber::new(&serial)?,
+        Validity::from_now(TLS_ALPN_CERTIFICATE_VALIDITY)?,
+        Name::default(),
+        spki,
+        &private_key,
+    )
+    .context("tls-alpn certificate")?;
+
+    cert.add_extension(&SubjectAltName(vec![GeneralName::DnsName(Ia5String::new(
+        domain,
+    )?)]))?;
+    cert.add_extension(&ExtendedKeyUsage(vec![ID_KP_SERVER_AUTH]))?;
+    cert.add_extension(&AcmeIdentifier(OctetString::new(proof)?))?;
+
+    let cert = cert
+        .build::<p256::ecdsa::DerSignature>()
+        .context("build tls-alpn certificate")?;
+
+    Ok((
+        cert.to_pem(pem::LineEnding::LF)?,
+        private_key.to_pkcs8_pem(pem::LineEnding::LF)?,
+    ))
+}
+
 /// Encapsulated certificate and private key.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Certificate {
@@ -80,6 +436,15 @@ impl Certificate {
         &self.private_key_pem
     }
 
+    /// The private key, e.g., for reusing it when [renewing] the certificate.
+    ///
+    /// [renewing]: crate::Account::renew()
+    pub fn signing_key(&self) -> eyre::Result<p256::ecdsa::SigningKey> {
+        Ok(ecdsa::SigningKey::<p256::NistP256>::from_pkcs8_pem(
+            &self.private_key_pem,
+        )?)
+    }
+
     /// The private key in DER encoding.
     pub fn private_key_der(&self) -> eyre::Result<Vec<u8>> {
         let private_key =
@@ -94,6 +459,9 @@ impl Certificate {
     }
 
     /// The issued certificate chain in DER format.
+    ///
+    /// Each certificate in the [PEM-encoded chain](Self::certificate) is returned separately, in
+    /// order, starting with the end-entity certificate. Useful for APIs which expect DER.
     pub fn certificate_chain(&self) -> eyre::Result<Vec<Vec<u8>>> {
         let mut rdr = BufReader::new(Cursor::new(self.certificate()));
 
@@ -103,6 +471,45 @@ impl Certificate {
             .map_err(Into::into)
     }
 
+    /// The issued certificate chain, split into individual PEM-encoded certificates.
+    ///
+    /// See [`CertChain`].
+    pub fn chain(&self) -> eyre::Result<CertChain> {
+        CertChain::parse(&self.certificate)
+    }
+
+    /// Returns the DNS names in the Subject Alternative Names of the end-entity certificate.
+    ///
+    /// Useful for confirming which domains the certificate actually covers.
+    pub fn dns_names(&self) -> eyre::Result<Vec<String>> {
+        let cert_chain = self.certificate_chain()?;
+        let cert_ee = cert_chain
+            .first() // EE cert is first
+            .ok_or_else(|| eyre!("no certificates in chain"))?;
+
+        Ok(crate::util::subject_alt_names(cert_ee)?
+            .into_iter()
+            .filter_map(|name| match name {
+                GeneralName::DnsName(dns) => Some(dns.to_string()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns the start of the end-entity certificate's validity period (`notBefore`).
+    pub fn valid_from(&self) -> eyre::Result<OffsetDateTime> {
+        let cert = self.end_entity_certificate()?;
+        to_offset_date_time(cert.tbs_certificate.validity.not_before)
+    }
+
+    /// Returns the end of the end-entity certificate's validity period (`notAfter`).
+    ///
+    /// Useful for scheduling renewal.
+    pub fn valid_until(&self) -> eyre::Result<OffsetDateTime> {
+        let cert = self.end_entity_certificate()?;
+        to_offset_date_time(cert.tbs_certificate.validity.not_after)
+    }
+
     /// Inspect the certificate to count the number of (whole) valid days left.
     ///
     /// It's up to the ACME API provider to decide how long an issued certificate is valid.
@@ -116,19 +523,340 @@ impl Certificate {
             return Ok(89);
         }
 
+        let diff = self.valid_until()? - OffsetDateTime::now_utc();
+
+        Ok(diff.whole_days())
+    }
+
+    /// Parses the end-entity certificate, i.e., the first certificate in the chain.
+    fn end_entity_certificate(&self) -> eyre::Result<x509_cert::Certificate> {
         let cert_chain = self.certificate_chain()?;
         let cert_ee = cert_chain
             .first() // EE cert is first
             .ok_or_else(|| eyre!("no certificates in chain"))?;
 
-        let cert = x509_cert::Certificate::from_der(cert_ee)?;
+        Ok(x509_cert::Certificate::from_der(cert_ee)?)
+    }
+}
+
+/// A PEM-encoded certificate chain, split into individual certificates.
+///
+/// ACME API providers return the end-entity (leaf) certificate followed by its intermediates as
+/// one concatenated PEM document. Useful when they are consumed separately, e.g., by servers
+/// which configure the leaf and the chain in different files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertChain {
+    /// PEM blocks, each ending in a newline. Never empty.
+    blocks: Vec<String>,
+}
+
+impl CertChain {
+    /// Splits a concatenated PEM document into its `CERTIFICATE` blocks.
+    ///
+    /// Text and other kinds of PEM blocks are ignored. Each block is decoded and re-encoded, but the
+    /// certificates themselves are not parsed. Fails if there are no certificates or a block is not
+    /// terminated or not valid PEM.
+    pub fn parse(pem: &str) -> eyre::Result<Self> {
+        let blocks = crate::util::pem_blocks(pem)?
+            .into_iter()
+            .filter(|(label, _block)| label == "CERTIFICATE")
+            .map(|(_label, block)| block.to_string())
+            .collect::<Vec<_>>();
 
-        let not_after = cert.tbs_certificate.validity.not_after.to_date_time();
-        // TODO: justify assume_utc
-        let not_after = PrimitiveDateTime::try_from(not_after).unwrap().assume_utc();
+        if blocks.is_empty() {
+            return Err(eyre!("no certificates in chain"));
+        }
 
-        let diff = not_after - OffsetDateTime::now_utc();
+        Ok(Self { blocks })
+    }
 
-        Ok(diff.whole_days())
+    /// Returns the PEM-encoded end-entity certificate, i.e., the first certificate in the chain.
+    pub fn leaf(&self) -> &str {
+        &self.blocks[0]
+    }
+
+    /// Returns the PEM-encoded intermediate certificates, i.e., all but the first certificate in
+    /// the chain, in order.
+    pub fn intermediates(&self) -> Vec<&str> {
+        self.blocks[1..].iter().map(String::as_str).collect()
+    }
+
+    /// Returns each PEM-encoded certificate in the chain, in order, starting with the leaf.
+    pub fn as_pem_blocks(&self) -> Vec<String> {
+        self.blocks.clone()
+    }
+
+    /// Returns the SHA-256 fingerprint of the DER-encoded leaf certificate.
+    ///
+    /// Useful for pinning and deduplicating certificates. See [`util::format_fingerprint()`] for
+    /// displaying it.
+    ///
+    /// [`util::format_fingerprint()`]: crate::util::format_fingerprint()
+    pub fn leaf_fingerprint_sha256(&self) -> eyre::Result<[u8; 32]> {
+        let (_label, leaf_der) = pem::decode_vec(self.leaf().as_bytes())
+            .map_err(|err| eyre!("Failed to decode leaf certificate PEM: {err}"))?;
+
+        Ok(crate::util::certificate_sha256_fingerprint(&leaf_der))
+    }
+}
+
+fn to_offset_date_time(time: x509_cert::time::Time) -> eyre::Result<OffsetDateTime> {
+    // X.509 validity times are always in UTC (RFC 5280 §4.1.2.5)
+    Ok(PrimitiveDateTime::try_from(time.to_date_time())?.assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+
+    use super::*;
+
+    #[test]
+    fn test_create_p256_key_with_rng() {
+        let key1 = create_p256_key_with_rng(&mut rand::rngs::StdRng::seed_from_u64(42));
+        let key2 = create_p256_key_with_rng(&mut rand::rngs::StdRng::seed_from_u64(42));
+        let key3 = create_p256_key_with_rng(&mut rand::rngs::StdRng::seed_from_u64(43));
+
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+    }
+
+    /// Returns a leaf and an issuer certificate, and the leaf with both in its chain.
+    fn leaf_and_issuer() -> (Certificate, Certificate, Certificate) {
+        let leaf = crate::test::self_signed_certificate("leaf.example.com");
+        let issuer = crate::test::self_signed_certificate("issuer.example.com");
+
+        let cert = Certificate::new(
+            leaf.private_key_pem.clone(),
+            format!("{}{}", leaf.certificate(), issuer.certificate()),
+        );
+
+        (leaf, issuer, cert)
+    }
+
+    #[test]
+    fn test_certificate_chain() {
+        let (leaf, issuer, cert) = leaf_and_issuer();
+
+        let chain = cert.certificate_chain().unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0], leaf.certificate_chain().unwrap()[0]);
+        assert_eq!(chain[1], issuer.certificate_chain().unwrap()[0]);
+        assert_eq!(cert.dns_names().unwrap(), ["leaf.example.com"]);
+    }
+
+    const PEM_A: &str = "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n";
+    const PEM_B: &str = "-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n";
+    const PEM_C: &str = "-----BEGIN CERTIFICATE-----\nCCCC\n-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn test_cert_chain_two_certs() {
+        let chain = CertChain::parse(&format!("{PEM_A}{PEM_B}")).unwrap();
+
+        assert_eq!(chain.leaf(), PEM_A);
+        assert_eq!(chain.intermediates(), [PEM_B]);
+        assert_eq!(chain.as_pem_blocks(), [PEM_A, PEM_B]);
+    }
+
+    #[test]
+    fn test_cert_chain_three_certs() {
+        // missing trailing newline and blank lines between blocks
+        let pem = format!("{PEM_A}\n{PEM_B}\n\n{}", PEM_C.trim_end());
+        let chain = CertChain::parse(&pem).unwrap();
+
+        assert_eq!(chain.leaf(), PEM_A);
+        assert_eq!(chain.intermediates(), [PEM_B, PEM_C]);
+        assert_eq!(chain.as_pem_blocks(), [PEM_A, PEM_B, PEM_C]);
+    }
+
+    #[test]
+    fn test_cert_chain_invalid() {
+        assert!(CertChain::parse("").is_err());
+        assert!(CertChain::parse("CERT HERE").is_err());
+
+        let unterminated = format!("{PEM_A}-----BEGIN CERTIFICATE-----\nBBBB\n");
+        assert!(CertChain::parse(&unterminated).is_err());
+
+        let invalid = "-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n";
+        assert!(CertChain::parse(invalid).is_err());
+    }
+
+    #[test]
+    fn test_certificate_chain_pem_blocks() {
+        let (leaf, issuer, cert) = leaf_and_issuer();
+
+        let chain = cert.chain().unwrap();
+        assert_eq!(chain.leaf(), leaf.certificate());
+        assert_eq!(chain.intermediates(), [issuer.certificate()]);
+    }
+
+    #[test]
+    fn test_cert_chain_leaf_fingerprint() {
+        let pem = format!("{}{PEM_A}", crate::test::FIXTURE_CERTIFICATE_PEM);
+        let chain = CertChain::parse(&pem).unwrap();
+
+        assert_eq!(
+            crate::util::format_fingerprint(&chain.leaf_fingerprint_sha256().unwrap()),
+            crate::test::FIXTURE_CERTIFICATE_SHA256,
+        );
+    }
+
+    #[test]
+    fn test_create_p256_key_from_seed() {
+        let key1 = create_p256_key_from_seed(&[1; 32]).unwrap();
+        let key2 = create_p256_key_from_seed(&[1; 32]).unwrap();
+        let key3 = create_p256_key_from_seed(&[2; 32]).unwrap();
+
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_eq!(key1.to_bytes()[..], [1; 32]);
+
+        for seed in [[0; 32], [0xFF; 32]] {
+            let err = create_p256_key_from_seed(&seed).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::InvalidKeySeed)
+            ));
+        }
+    }
+
+    fn csr_extension(
+        csr: &x509_cert::request::CertReq,
+        oid: ObjectIdentifier,
+    ) -> Option<Extension> {
+        csr.info
+            .attributes
+            .iter()
+            .flat_map(|attr| attr.values.iter())
+            .filter_map(|value| value.decode_as::<Vec<Extension>>().ok())
+            .flatten()
+            .find(|ext| ext.extn_id == oid)
+    }
+
+    fn has_subject_alt_name(csr: &x509_cert::request::CertReq) -> bool {
+        csr_extension(csr, SubjectAltName::OID).is_some()
+    }
+
+    #[test]
+    fn test_create_csr_with_must_staple() {
+        let key = create_p256_key();
+        let domains = ["acme-test.example.com"];
+
+        let csr = create_csr(&key, &domains, &CsrParams::new()).unwrap();
+        assert!(csr_extension(&csr, TlsFeature::OID).is_none());
+
+        let params = CsrParams::new().with_must_staple(true);
+        let csr = create_csr(&key, &domains, &params).unwrap();
+        let ext = csr_extension(&csr, TlsFeature::OID).unwrap();
+        assert!(!ext.critical);
+
+        // SEQUENCE OF INTEGER containing status_request (5)
+        assert_eq!(ext.extn_value.as_bytes(), [0x30, 0x03, 0x02, 0x01, 0x05]);
+    }
+
+    #[test]
+    fn test_create_csr_with_params() {
+        let key = create_p256_key();
+
+        let csr = create_csr(&key, &["acme-test.example.com"], &CsrParams::new()).unwrap();
+        assert_eq!(csr.info.subject.to_string(), "CN=acme-test.example.com");
+        assert!(!has_subject_alt_name(&csr));
+
+        let params = CsrParams::new()
+            .with_common_name("Example Service")
+            .with_organization("Example, Inc.")
+            .with_organizational_unit("Platform");
+        let csr = create_csr(&key, &["acme-test.example.com"], &params).unwrap();
+        assert_eq!(
+            csr.info.subject.to_string(),
+            r"CN=Example Service,OU=Platform,O=Example\, Inc.",
+        );
+        assert!(has_subject_alt_name(&csr));
+    }
+
+    #[test]
+    fn test_create_csr_idn() {
+        let key = create_p256_key();
+        let csr = create_csr(
+            &key,
+            &["bücher.example", "*.münchen.example"],
+            &CsrParams::new(),
+        )
+        .unwrap();
+
+        assert_eq!(csr.info.subject.to_string(), "CN=xn--bcher-kva.example");
+
+        let csr_der = der::Encode::to_der(&csr).unwrap();
+        let contains = |needle: &[u8]| csr_der.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"*.xn--mnchen-3ya.example"));
+
+        assert!(create_csr(&key, &["bücher..example"], &CsrParams::new()).is_err());
+    }
+
+    #[test]
+    fn test_check_csr_identifiers() {
+        use der::Encode as _;
+
+        let key = create_p256_key();
+        let identifiers = [
+            api::Identifier::dns("acme-test.example.com"),
+            api::Identifier::dns("www.acme-test.example.com"),
+        ];
+
+        let csr = create_csr(
+            &key,
+            &["www.acme-test.example.com", "ACME-test.example.com"],
+            &CsrParams::new(),
+        )
+        .unwrap();
+        check_csr_identifiers(&csr.to_der().unwrap(), &identifiers).unwrap();
+
+        let csr = create_csr(
+            &key,
+            &["acme-test.example.com", "other.example.com"],
+            &CsrParams::new(),
+        )
+        .unwrap();
+        let err = check_csr_identifiers(&csr.to_der().unwrap(), &identifiers).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::CsrIdentifiersMismatch { missing, unexpected })
+                if missing == &["www.acme-test.example.com"] && unexpected == &["other.example.com"],
+        ));
+    }
+
+    #[test]
+    fn test_create_tls_alpn_certificate() {
+        let proof = [42; 32];
+        let (cert_pem, key_pem) =
+            create_tls_alpn_certificate("acme-test.example.com", proof).unwrap();
+
+        let cert = Certificate::parse(key_pem, cert_pem).unwrap();
+        assert_eq!(cert.dns_names().unwrap(), ["acme-test.example.com"]);
+
+        let cert = cert.end_entity_certificate().unwrap();
+        let ext = cert
+            .tbs_certificate
+            .extensions
+            .iter()
+            .flatten()
+            .find(|ext| ext.extn_id == AcmeIdentifier::OID)
+            .unwrap();
+        assert!(ext.critical);
+
+        // DER-encoded OCTET STRING containing the proof
+        assert_eq!(ext.extn_value.as_bytes()[..2], [0x04, 0x20]);
+        assert_eq!(ext.extn_value.as_bytes()[2..], proof);
+    }
+
+    #[test]
+    fn test_validity_and_dns_names() {
+        let cert = crate::test::self_signed_certificate("acme-test.example.com");
+
+        // rcgen's default validity period
+        assert_eq!(cert.valid_from().unwrap().year(), 1975);
+        assert_eq!(cert.valid_until().unwrap().year(), 4096);
+
+        assert_eq!(cert.dns_names().unwrap(), ["acme-test.example.com"]);
     }
 }
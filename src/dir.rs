@@ -1,15 +1,24 @@
-use std::sync::Arc;
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
+
+use sha2::{Digest as _, Sha256};
 
 use crate::{
-    acc::AcmeKey,
+    acc::{AcmeKey, PersistedAccount},
     api,
-    req::{req_expect_header, req_get, req_handle_error},
-    trans::{NoncePool, Transport},
-    Account,
+    http::HttpClient,
+    req::{
+        http_client, req_body_error, req_expect_header, req_get, req_handle_error, req_json,
+        DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT,
+    },
+    trans::{NoncePool, RewriteUrl, Transport, DEFAULT_MAX_BAD_NONCE_RETRIES},
+    Account, Certificate, Error, HttpTransport, JwsFormat, KeyKind, PollConfig, RateLimitRetry,
+    RevocationReason,
 };
 
 const LETSENCRYPT_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
 const LETSENCRYPT_STAGING_URL: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
+const ZEROSSL_URL: &str = "https://acme.zerossl.com/v2/DV90";
+const GOOGLE_TRUST_SERVICES_URL: &str = "https://dv.acme-v02.api.pki.goog/directory";
 
 /// Enumeration of known ACME API directories.
 #[derive(Debug, Clone)]
@@ -25,43 +34,380 @@ pub enum DirectoryUrl<'a> {
     /// certificate is not supposed to be in any trust chains.
     LetsEncryptStaging,
 
+    /// The ZeroSSL directory.
+    ///
+    /// Requires External Account Binding (EAB) credentials from the ZeroSSL dashboard; see
+    /// [`Directory::external_account_required()`].
+    ZeroSsl,
+
+    /// The Google Trust Services directory.
+    ///
+    /// Requires External Account Binding (EAB) credentials from Google Cloud; see
+    /// [`Directory::external_account_required()`].
+    GoogleTrustServices,
+
     /// Provide an arbitrary director URL to connect to.
     Other(&'a str),
 }
 
-impl DirectoryUrl<'_> {
-    fn to_url(&self) -> &str {
+impl<'a> DirectoryUrl<'a> {
+    /// Returns the URL of the directory.
+    ///
+    /// Useful for logging which environment is used.
+    pub fn as_str(&self) -> &'a str {
         match self {
             DirectoryUrl::LetsEncrypt => LETSENCRYPT_URL,
             DirectoryUrl::LetsEncryptStaging => LETSENCRYPT_STAGING_URL,
+            DirectoryUrl::ZeroSsl => ZEROSSL_URL,
+            DirectoryUrl::GoogleTrustServices => GOOGLE_TRUST_SERVICES_URL,
             DirectoryUrl::Other(url) => url,
         }
     }
 }
 
+/// Maximum size of a terms of service document fetched by
+/// [`Directory::terms_of_service_sha256()`].
+const MAX_TERMS_OF_SERVICE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Builder for a [`Directory`] with non-default configuration.
+///
+/// Created using [`Directory::builder()`].
+#[derive(Debug, Clone)]
+pub struct DirectoryBuilder<'a> {
+    url: DirectoryUrl<'a>,
+    poll_config: PollConfig,
+    max_concurrent_orders: Option<usize>,
+    account_key_kind: KeyKind,
+    rewrite_url: Option<RewriteUrl>,
+    http_client: Option<HttpClient>,
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    max_bad_nonce_retries: u32,
+    rate_limit_retry: Option<RateLimitRetry>,
+}
+
+impl<'a> DirectoryBuilder<'a> {
+    fn new(url: DirectoryUrl<'a>) -> Self {
+        Self {
+            url,
+            poll_config: PollConfig::default(),
+            max_concurrent_orders: None,
+            account_key_kind: KeyKind::default(),
+            rewrite_url: None,
+            http_client: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_bad_nonce_retries: DEFAULT_MAX_BAD_NONCE_RETRIES,
+            rate_limit_retry: None,
+        }
+    }
+
+    /// Sets the HTTP client used for all requests to the ACME API, including fetching the
+    /// directory.
+    ///
+    /// Useful for enforcing a TLS policy, e.g., a minimum TLS version or a custom rustls
+    /// `ClientConfig` using [`reqwest::ClientBuilder::use_preconfigured_tls()`]:
+    ///
+    /// ```no_run
+    /// # async fn fetch() -> eyre::Result<acme::Directory> {
+    /// let client = reqwest::Client::builder()
+    ///     .min_tls_version(reqwest::tls::Version::TLS_1_2)
+    ///     .build()?;
+    ///
+    /// acme::Directory::builder(acme::DirectoryUrl::LetsEncrypt)
+    ///     .http_client(client)
+    ///     .fetch()
+    ///     .await
+    /// # }
+    /// ```
+    ///
+    /// Defaults to a client with the [connect](Self::connect_timeout) and
+    /// [request](Self::request_timeout) timeouts set on this builder. Configure timeouts on custom
+    /// clients as appropriate.
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(HttpClient::new(http_client));
+        self
+    }
+
+    /// Sets a custom transport used for all HTTP requests to the ACME API, including fetching the
+    /// directory.
+    ///
+    /// Useful for answering requests in-process in tests, or for alternative HTTP backends. See
+    /// [`HttpTransport`]. Overrides the [HTTP client](Self::http_client) and timeouts.
+    pub fn http_transport(mut self, http_transport: impl HttpTransport + 'static) -> Self {
+        self.http_client = Some(HttpClient::new(http_transport));
+        self
+    }
+
+    /// Sets the time limit for establishing a connection to the ACME API.
+    ///
+    /// Not used if a custom [HTTP client](Self::http_client) is set.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Sets the time limit for each request to the ACME API, from connecting until the response
+    /// body has been read.
+    ///
+    /// Requests that take longer, including reading the response body, fail with
+    /// [`Error::Timeout`]. Not used if a custom
+    /// [HTTP client](Self::http_client) is set.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Sets a function that rewrites URLs returned by the ACME API before they are requested.
+    ///
+    /// Useful behind proxies, when the ACME API returns URLs (e.g., for nonces, orders,
+    /// authorizations, finalization, and certificates) pointing at a host that cannot be reached
+    /// directly. Requests are still signed for the original URLs, which the ACME API expects.
+    ///
+    /// The directory URL itself is not rewritten.
+    pub fn rewrite_url(
+        mut self,
+        rewrite_url: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.rewrite_url = Some(RewriteUrl::new(rewrite_url));
+        self
+    }
+
+    /// Sets the kind of key expected when loading accounts from PEM, and generated by
+    /// [`Directory::register_account()`].
+    ///
+    /// Loading an account whose key is of a different kind fails early, before any request is made
+    /// to the ACME API, with [`Error::KeyKindMismatch`].
+    ///
+    /// Defaults to [`KeyKind::EcdsaP256`].
+    pub fn account_key_kind(mut self, account_key_kind: KeyKind) -> Self {
+        self.account_key_kind = account_key_kind;
+        self
+    }
+
+    /// Sets the default poll configuration used by accounts created from this directory.
+    ///
+    /// Defaults to [`PollConfig::lets_encrypt()`].
+    pub fn poll_config(mut self, poll_config: PollConfig) -> Self {
+        self.poll_config = poll_config;
+        self
+    }
+
+    /// Sets the maximum number of times each request to the ACME API is retried with a fresh nonce
+    /// after the ACME API rejects its nonce with a `badNonce` error.
+    ///
+    /// Bad nonces are expected occasionally, especially when an account is used concurrently. See
+    /// [RFC 8555 §6.5].
+    ///
+    /// Defaults to 3.
+    ///
+    /// [RFC 8555 §6.5]: https://datatracker.ietf.org/doc/html/rfc8555#section-6.5
+    pub fn max_bad_nonce_retries(mut self, max_bad_nonce_retries: u32) -> Self {
+        self.max_bad_nonce_retries = max_bad_nonce_retries;
+        self
+    }
+
+    /// Sets how requests rejected by the ACME API's rate limits are retried.
+    ///
+    /// If the request is still rate limited after the last retry, the error for the last attempt
+    /// is returned.
+    ///
+    /// Defaults to not retrying.
+    pub fn rate_limit_retry(mut self, rate_limit_retry: RateLimitRetry) -> Self {
+        self.rate_limit_retry = Some(rate_limit_retry);
+        self
+    }
+
+    /// Sets the maximum number of orders that can be in-flight simultaneously for each account.
+    ///
+    /// An order is in-flight from its creation using [`Account::new_order()`] until all of its
+    /// façades ([`NewOrder`], [`CsrOrder`], and [`CertOrder`]) are dropped. This helps respect
+    /// concurrency limits of the ACME API provider when an account is shared by many tasks.
+    ///
+    /// Defaults to unlimited.
+    ///
+    /// [`NewOrder`]: crate::order::NewOrder
+    /// [`CsrOrder`]: crate::order::CsrOrder
+    /// [`CertOrder`]: crate::order::CertOrder
+    pub fn max_concurrent_orders(mut self, max_concurrent_orders: NonZeroUsize) -> Self {
+        self.max_concurrent_orders = Some(max_concurrent_orders.get());
+        self
+    }
+
+    /// Fetches the directory using this configuration.
+    pub async fn fetch(self) -> eyre::Result<Directory> {
+        let http_client = self.http_client.unwrap_or_else(|| {
+            HttpClient::new(http_client(self.connect_timeout, self.request_timeout))
+        });
+
+        let url = self.url.as_str();
+        let res = req_handle_error(req_get(&http_client, url).await?)
+            .await
+            .map_err(|problem| Error::Problem {
+                url: url.to_owned(),
+                problem,
+            })?;
+        let raw = req_json::<serde_json::Value>(res).await?;
+        let api_directory = serde_json::from_value::<api::Directory>(raw.clone())?;
+        let nonce_url = match &self.rewrite_url {
+            Some(rewrite_url) => rewrite_url.apply(&api_directory.new_nonce),
+            None => api_directory.new_nonce.clone(),
+        };
+        let nonce_pool = Arc::new(NoncePool::new(&nonce_url, http_client.clone()));
+
+        Ok(Directory {
+            nonce_pool,
+            api_directory,
+            raw,
+            poll_config: self.poll_config,
+            max_concurrent_orders: self.max_concurrent_orders,
+            max_bad_nonce_retries: self.max_bad_nonce_retries,
+            rate_limit_retry: self.rate_limit_retry,
+            account_key_kind: self.account_key_kind,
+            rewrite_url: self.rewrite_url,
+            http_client,
+        })
+    }
+}
+
 /// Entry point for accessing an ACME API.
 #[derive(Clone)]
 pub struct Directory {
     nonce_pool: Arc<NoncePool>,
     api_directory: api::Directory,
+    raw: serde_json::Value,
+    poll_config: PollConfig,
+    max_concurrent_orders: Option<usize>,
+    max_bad_nonce_retries: u32,
+    rate_limit_retry: Option<RateLimitRetry>,
+    account_key_kind: KeyKind,
+    rewrite_url: Option<RewriteUrl>,
+    http_client: HttpClient,
 }
 
 impl Directory {
     /// Create a directory over a persistence implementation and directory url.
+    ///
+    /// Use [`Directory::builder()`] for non-default configuration, e.g., a custom
+    /// [HTTP client](DirectoryBuilder::http_client).
     pub async fn fetch(url: DirectoryUrl<'_>) -> eyre::Result<Directory> {
-        let res = req_handle_error(req_get(url.to_url()).await).await?;
-        let api_directory = res.json::<api::Directory>().await?;
-        let nonce_pool = Arc::new(NoncePool::new(&api_directory.new_nonce));
+        Self::builder(url).fetch().await
+    }
 
-        Ok(Directory {
-            nonce_pool,
-            api_directory,
-        })
+    /// Fetches the directory and registers an account, failing if the whole sequence takes longer
+    /// than `total_timeout`.
+    ///
+    /// Bootstrapping makes several requests (directory, newNonce, newAccount); while each of them
+    /// is bounded by the HTTP client's timeouts, a sequence of slow responses is not. Use this for
+    /// fail-fast service initialization.
+    ///
+    /// Returns [`Error::BootstrapTimeout`] if the time limit is exceeded.
+    pub async fn from_url_with_timeout(
+        url: DirectoryUrl<'_>,
+        contact: Option<Vec<String>>,
+        total_timeout: Duration,
+    ) -> eyre::Result<(Directory, Account)> {
+        let dir_url = url.as_str().to_owned();
+
+        let bootstrap = async {
+            let dir = Self::fetch(url).await?;
+            let acc = dir.register_account(contact).await?;
+            eyre::Ok((dir, acc))
+        };
+
+        match tokio::time::timeout(total_timeout, bootstrap).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::BootstrapTimeout {
+                url: dir_url,
+                timeout: total_timeout,
+            }
+            .into()),
+        }
     }
 
+    /// Returns a builder for a directory with non-default configuration.
+    pub fn builder(url: DirectoryUrl<'_>) -> DirectoryBuilder<'_> {
+        DirectoryBuilder::new(url)
+    }
+
+    /// Registers an account with a new key.
+    ///
+    /// This agrees to the ACME API provider's [terms of service](Self::terms_of_service) on the
+    /// caller's behalf. Use [`register_account_agreeing()`](Self::register_account_agreeing) to
+    /// make the agreement explicit.
     pub async fn register_account(&self, contact: Option<Vec<String>>) -> eyre::Result<Account> {
-        let acme_key = AcmeKey::new();
-        self.upsert_account(acme_key, contact).await
+        let acme_key = AcmeKey::new_of_kind(self.account_key_kind);
+        self.upsert_account(acme_key, contact, true).await
+    }
+
+    /// Registers an account with a new key, agreeing to the ACME API provider's
+    /// [terms of service](Self::terms_of_service) only if `agree_tos` is true.
+    ///
+    /// Fails early with [`Error::TermsOfServiceNotAgreed`], without contacting the ACME API, if
+    /// the provider advertises terms of service or requires external account binding and
+    /// `agree_tos` is false.
+    pub async fn register_account_agreeing(
+        &self,
+        contact: Option<Vec<String>>,
+        agree_tos: bool,
+    ) -> eyre::Result<Account> {
+        if !agree_tos && (self.terms_of_service().is_some() || self.external_account_required()) {
+            return Err(Error::TermsOfServiceNotAgreed {
+                terms_of_service: self.terms_of_service().map(ToOwned::to_owned),
+            }
+            .into());
+        }
+
+        let acme_key = AcmeKey::new_of_kind(self.account_key_kind);
+        self.upsert_account(acme_key, contact, agree_tos).await
+    }
+
+    /// Registers an account with a new key of the [configured kind] generated using the random
+    /// number generator `rng`.
+    ///
+    /// See [`create_p256_key_with_rng()`] for the security implications of using an RNG other than
+    /// the operating system's.
+    ///
+    /// [configured kind]: DirectoryBuilder::account_key_kind()
+    /// [`create_p256_key_with_rng()`]: crate::create_p256_key_with_rng()
+    pub async fn register_account_with_rng(
+        &self,
+        rng: &mut (impl rand::CryptoRng + rand::RngCore),
+        contact: Option<Vec<String>>,
+    ) -> eyre::Result<Account> {
+        let acme_key = AcmeKey::new_of_kind_with_rng(self.account_key_kind, rng);
+        self.upsert_account(acme_key, contact, true).await
+    }
+
+    /// Registers an account with a key derived from `seed`.
+    ///
+    /// Registering with the same seed again returns the same account. See
+    /// [`create_p256_key_from_seed()`] for requirements on the seed and the security implications.
+    ///
+    /// Only P-256 keys can be derived from a seed; fails with [`Error::KeyKindMismatch`] if a
+    /// different [kind of account key] is configured.
+    ///
+    /// [`create_p256_key_from_seed()`]: crate::create_p256_key_from_seed()
+    /// [kind of account key]: DirectoryBuilder::account_key_kind()
+    pub async fn register_account_with_seed(
+        &self,
+        seed: &[u8; 32],
+        contact: Option<Vec<String>>,
+    ) -> eyre::Result<Account> {
+        if self.account_key_kind != KeyKind::EcdsaP256 {
+            return Err(Error::KeyKindMismatch {
+                expected: self.account_key_kind,
+                found: KeyKind::EcdsaP256.to_string(),
+            }
+            .into());
+        }
+
+        let acme_key = AcmeKey::from_key(crate::create_p256_key_from_seed(seed)?);
+        self.upsert_account(acme_key, contact, true).await
     }
 
     pub async fn load_account(
@@ -69,11 +415,53 @@ impl Directory {
         private_key_pem: &str,
         contact: Option<Vec<String>>,
     ) -> eyre::Result<Account> {
+        self.account_key_kind.check_pem(private_key_pem)?;
         let acme_key = AcmeKey::from_pem(private_key_pem)?;
-        self.upsert_account(acme_key, contact).await
+        self.upsert_account(acme_key, contact, true).await
+    }
+
+    /// Restores an account saved using [`Account::to_persisted()`].
+    ///
+    /// The account object is fetched from the ACME API to check that the account still exists. If a
+    /// server key thumbprint was persisted, the account key reported by the ACME API is checked
+    /// against it, failing with [`Error::AccountKeyMismatch`] if they differ.
+    pub async fn account_from_persisted(
+        &self,
+        persisted: PersistedAccount,
+    ) -> eyre::Result<Account> {
+        self.account_key_kind
+            .check_pem(&persisted.private_key_pem)?;
+        let acme_key = AcmeKey::from_pem(&persisted.private_key_pem)?;
+
+        let mut transport = self.transport(acme_key);
+        transport.set_key_id(persisted.account_url.clone());
+
+        let res = transport
+            .call_kid(&persisted.account_url, &api::EmptyString)
+            .await?;
+        let api_account = req_json::<api::Account>(res).await?;
+
+        let account = Account::new(
+            transport,
+            api_account,
+            self.api_directory.clone(),
+            self.poll_config.clone(),
+            self.max_concurrent_orders,
+        );
+
+        if let Some(pinned) = &persisted.server_key_thumbprint {
+            account.verify_server_key(pinned)?;
+        }
+
+        Ok(account)
     }
 
+    /// Looks up the existing account for the private key `private_key_pem`, without creating one.
+    ///
+    /// Useful for persisting only the account key and re-deriving the account on startup. Fails
+    /// with [`Error::AccountNotFound`] if the ACME API has no account for the key.
     pub async fn load_existing_account(&self, private_key_pem: &str) -> eyre::Result<Account> {
+        self.account_key_kind.check_pem(private_key_pem)?;
         let acme_key = AcmeKey::from_pem(private_key_pem)?;
 
         let acc = api::Account {
@@ -81,15 +469,22 @@ impl Directory {
             ..Default::default()
         };
 
-        let mut transport = Transport::new(Arc::clone(&self.nonce_pool), acme_key);
+        let mut transport = self.transport(acme_key);
 
         let res = transport
             .call_jwk(&self.api_directory.new_account, &acc)
-            .await?;
+            .await
+            .map_err(|err| match err.downcast::<Error>() {
+                Ok(Error::Problem { url, problem }) if problem.is_account_does_not_exist() => {
+                    Error::AccountNotFound { url }.into()
+                }
+                Ok(err) => err.into(),
+                Err(err) => err,
+            })?;
 
         let kid = req_expect_header(&res, "location")?;
         log::debug!("Key ID is: {kid}");
-        let api_account = res.json::<api::Account>().await?;
+        let api_account = req_json::<api::Account>(res).await?;
 
         // fill in the server returned key ID
         transport.set_key_id(kid);
@@ -98,6 +493,8 @@ impl Directory {
             transport,
             api_account,
             self.api_directory.clone(),
+            self.poll_config.clone(),
+            self.max_concurrent_orders,
         ))
     }
 
@@ -105,26 +502,21 @@ impl Directory {
         &self,
         acme_key: AcmeKey,
         contact: Option<Vec<String>>,
+        terms_of_service_agreed: bool,
     ) -> eyre::Result<Account> {
         // Prepare making a call to newAccount. This is fine to do both for new
         // keys and existing. For existing the spec says to return a 200 with
         // the Location header set to the key ID (kid).
-        let acc = api::Account {
-            // TODO: ensure email contains no hfields or more than one addr-spec in the to component
-            // see https://datatracker.ietf.org/doc/html/rfc8555#section-7.3
-            contact,
-            terms_of_service_agreed: Some(true),
-            ..Default::default()
-        };
+        let acc = new_account_payload(contact, terms_of_service_agreed)?;
 
-        let mut transport = Transport::new(Arc::clone(&self.nonce_pool), acme_key);
+        let mut transport = self.transport(acme_key);
         let res = transport
             .call_jwk(&self.api_directory.new_account, &acc)
             .await?;
 
         let kid = req_expect_header(&res, "location")?;
         log::debug!("Key ID is: {kid}");
-        let api_account = res.json::<api::Account>().await?;
+        let api_account = req_json::<api::Account>(res).await?;
 
         // fill in the server returned key ID
         transport.set_key_id(kid);
@@ -133,21 +525,258 @@ impl Directory {
             transport,
             api_account,
             self.api_directory.clone(),
+            self.poll_config.clone(),
+            self.max_concurrent_orders,
         ))
     }
 
+    /// Signs the newAccount request that [`load_account()`](Self::load_account) would send for
+    /// `private_key_pem` and `contact`, without sending it.
+    ///
+    /// Useful for debugging interoperability with ACME API providers; the protected header embeds
+    /// the account's public key (`jwk`) rather than a key ID. ACME API providers only accept
+    /// [`JwsFormat::Flattened`].
+    ///
+    /// A nonce is consumed from the pool each time this is called.
+    pub async fn sign_new_account(
+        &self,
+        private_key_pem: &str,
+        contact: Option<Vec<String>>,
+        format: JwsFormat,
+    ) -> eyre::Result<String> {
+        self.account_key_kind.check_pem(private_key_pem)?;
+        let acme_key = AcmeKey::from_pem(private_key_pem)?;
+
+        let transport = self.transport(acme_key);
+        transport
+            .sign_jwk(
+                &self.api_directory.new_account,
+                &new_account_payload(contact, true)?,
+                format,
+            )
+            .await
+    }
+
+    /// Returns a transport for calls using `acme_key`, configured from this directory.
+    fn transport(&self, acme_key: AcmeKey) -> Transport {
+        let mut transport = Transport::new(
+            Arc::clone(&self.nonce_pool),
+            acme_key,
+            self.http_client.clone(),
+        );
+        transport.set_max_bad_nonce_retries(self.max_bad_nonce_retries);
+        transport.set_rate_limit_retry(self.rate_limit_retry.clone());
+        transport.set_rewrite_url(self.rewrite_url.clone());
+        transport
+    }
+
+    /// Revokes a certificate for the reason given, signing the request with the certificate's own
+    /// private key instead of an account key.
+    ///
+    /// Useful when the account that ordered the certificate is not available. See [RFC 8555 §7.6].
+    ///
+    /// [RFC 8555 §7.6]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.6
+    pub async fn revoke_certificate_with_key(
+        &self,
+        cert: &Certificate,
+        reason: RevocationReason,
+    ) -> eyre::Result<()> {
+        let revocation = crate::acc::revocation(cert, reason)?;
+
+        let transport = self.transport(AcmeKey::from_key(cert.signing_key()?));
+        transport
+            .call_jwk(&self.api_directory.revoke_cert, &revocation)
+            .await?;
+
+        Ok(())
+    }
+
     /// Returns a reference to the directory's API object.
     ///
     /// Useful for debugging.
     pub fn api_directory(&self) -> &api::Directory {
         &self.api_directory
     }
+
+    /// Fetches the terms of service document linked from the directory and returns its SHA-256
+    /// digest.
+    ///
+    /// Useful for recording exactly which version of the terms of service was agreed to. Redirects
+    /// are followed. Documents larger than 16 MiB are rejected. Returns `None` if the directory does
+    /// not link to any terms of service.
+    pub async fn terms_of_service_sha256(&self) -> eyre::Result<Option<[u8; 32]>> {
+        let Some(url) = self.terms_of_service() else {
+            return Ok(None);
+        };
+
+        let mut res = req_handle_error(req_get(&self.http_client, url).await?)
+            .await
+            .map_err(|problem| Error::Problem {
+                url: url.to_owned(),
+                problem,
+            })?;
+
+        if res
+            .content_length()
+            .is_some_and(|len| len > MAX_TERMS_OF_SERVICE_SIZE as u64)
+        {
+            return Err(eyre::eyre!(
+                "Terms of service document is larger than {MAX_TERMS_OF_SERVICE_SIZE} bytes"
+            ));
+        }
+
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+
+        while let Some(chunk) = res.chunk().await.map_err(|err| req_body_error(url, err))? {
+            size += chunk.len();
+
+            if size > MAX_TERMS_OF_SERVICE_SIZE {
+                return Err(eyre::eyre!(
+                    "Terms of service document is larger than {MAX_TERMS_OF_SERVICE_SIZE} bytes"
+                ));
+            }
+
+            hasher.update(&chunk);
+        }
+
+        Ok(Some(hasher.finalize().into()))
+    }
+
+    /// Returns the directory object exactly as returned by the ACME API provider.
+    ///
+    /// Useful for reading non-standard or vendor-specific fields not modelled by
+    /// [`api_directory()`](Self::api_directory).
+    pub fn raw(&self) -> &serde_json::Value {
+        &self.raw
+    }
+
+    /// Returns a map of the certificate profiles offered by the ACME API provider to their
+    /// descriptions.
+    ///
+    /// Profiles advertised without descriptions map to an empty string. Returns an empty map if no
+    /// profiles are offered.
+    pub fn profile_descriptions(&self) -> HashMap<String, String> {
+        self.api_directory
+            .meta
+            .as_ref()
+            .map(api::DirectoryMeta::profile_descriptions)
+            .unwrap_or_default()
+    }
+
+    /// Returns the URL of the ACME API provider's current terms of service, if advertised.
+    ///
+    /// See [`terms_of_service_sha256()`](Self::terms_of_service_sha256) for detecting changes.
+    pub fn terms_of_service(&self) -> Option<&str> {
+        self.meta()?.terms_of_service.as_deref()
+    }
+
+    /// Returns the hostnames that the ACME API provider recognizes in CAA records.
+    pub fn caa_identities(&self) -> &[String] {
+        self.meta()
+            .and_then(|meta| meta.caa_identities.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the URL of a website with more information about the ACME API provider, if
+    /// advertised.
+    pub fn website(&self) -> Option<&str> {
+        self.meta()?.website.as_deref()
+    }
+
+    /// Returns true if the ACME API provider requires new accounts to be bound to an external
+    /// account.
+    pub fn external_account_required(&self) -> bool {
+        self.meta()
+            .and_then(|meta| meta.external_account_required)
+            .unwrap_or(false)
+    }
+
+    fn meta(&self) -> Option<&api::DirectoryMeta> {
+        self.api_directory.meta.as_ref()
+    }
+
+    /// Returns the default poll configuration used by accounts created from this directory.
+    pub fn poll_config(&self) -> &PollConfig {
+        &self.poll_config
+    }
+}
+
+/// Returns the payload of a newAccount request.
+///
+/// The `termsOfServiceAgreed` field is omitted unless `terms_of_service_agreed` is true.
+fn new_account_payload(
+    contact: Option<Vec<String>>,
+    terms_of_service_agreed: bool,
+) -> eyre::Result<api::Account> {
+    let contact = contact
+        .map(|contact| contact.iter().map(|c| normalize_contact(c)).collect())
+        .transpose()?;
+
+    Ok(api::Account {
+        contact,
+        terms_of_service_agreed: terms_of_service_agreed.then_some(true),
+        ..Default::default()
+    })
+}
+
+/// Prefixes plain email addresses with `mailto:` after checking their syntax.
+///
+/// Contacts that already have a scheme are returned unchanged. Fails with
+/// [`Error::InvalidContact`] for email addresses that are malformed or that contain more than the
+/// single address allowed by [RFC 8555 §7.3].
+///
+/// [RFC 8555 §7.3]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.3
+fn normalize_contact(contact: &str) -> eyre::Result<String> {
+    if contact.contains(':') {
+        return Ok(contact.to_owned());
+    }
+
+    let is_valid = contact.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && !domain.is_empty()
+            && !domain.contains('@')
+            && !contact.contains(|c: char| c.is_whitespace() || matches!(c, ',' | '?' | '<' | '>'))
+    });
+
+    if !is_valid {
+        return Err(Error::InvalidContact {
+            contact: contact.to_owned(),
+        }
+        .into());
+    }
+
+    Ok(format!("mailto:{contact}"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_directory_url_as_str() {
+        assert_eq!(
+            DirectoryUrl::LetsEncrypt.as_str(),
+            "https://acme-v02.api.letsencrypt.org/directory",
+        );
+        assert_eq!(
+            DirectoryUrl::LetsEncryptStaging.as_str(),
+            "https://acme-staging-v02.api.letsencrypt.org/directory",
+        );
+        assert_eq!(
+            DirectoryUrl::ZeroSsl.as_str(),
+            "https://acme.zerossl.com/v2/DV90",
+        );
+        assert_eq!(
+            DirectoryUrl::GoogleTrustServices.as_str(),
+            "https://dv.acme-v02.api.pki.goog/directory",
+        );
+        assert_eq!(
+            DirectoryUrl::Other("https://acme.test/directory").as_str(),
+            "https://acme.test/directory",
+        );
+    }
+
     #[tokio::test]
     async fn test_create_directory() {
         let server = crate::test::with_directory_server();
@@ -156,6 +785,391 @@ mod tests {
         let _dir = Directory::fetch(url).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_register_account_with_seed() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc1 = dir
+            .register_account_with_seed(&[1; 32], None)
+            .await
+            .unwrap();
+        let acc2 = dir
+            .register_account_with_seed(&[1; 32], None)
+            .await
+            .unwrap();
+        let acc3 = dir
+            .register_account_with_seed(&[2; 32], None)
+            .await
+            .unwrap();
+
+        assert_eq!(acc1.thumbprint().unwrap(), acc2.thumbprint().unwrap());
+        assert_ne!(acc1.thumbprint().unwrap(), acc3.thumbprint().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_register_account_with_rng_p384() {
+        use rand::SeedableRng as _;
+
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .account_key_kind(KeyKind::EcdsaP384)
+            .fetch()
+            .await
+            .unwrap();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let acc = dir.register_account_with_rng(&mut rng, None).await.unwrap();
+        let pem = acc.acme_private_key_pem().unwrap();
+        assert_eq!(KeyKind::of_pem(&pem).unwrap(), KeyKind::EcdsaP384);
+
+        let err = dir
+            .register_account_with_seed(&[1; 32], None)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::KeyKindMismatch {
+                expected: KeyKind::EcdsaP384,
+                ..
+            }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_directory_with_poll_config() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .poll_config(PollConfig::step_ca())
+            .fetch()
+            .await
+            .unwrap();
+
+        assert_eq!(dir.poll_config(), &PollConfig::step_ca());
+    }
+
+    #[tokio::test]
+    async fn test_from_url_with_timeout() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let (_dir, acc) = Directory::from_url_with_timeout(url, None, Duration::from_secs(30))
+            .await
+            .unwrap();
+        assert!(acc.api_account().is_status_valid());
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let Err(err) = Directory::from_url_with_timeout(url, None, Duration::ZERO).await else {
+            panic!("bootstrap should time out");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::BootstrapTimeout { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_certificate_with_key() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/revoke-cert", "/acme/revoke-cert-with-key"))
+            .fetch()
+            .await
+            .unwrap();
+
+        let cert = crate::test::revocable_certificate();
+
+        dir.revoke_certificate_with_key(&cert, RevocationReason::Superseded)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_existing_account_not_found() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let pem = crate::generate_account_key(KeyKind::EcdsaP256).unwrap();
+        dir.load_existing_account(&pem).await.unwrap();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-acct", "/acme/new-acct-missing"))
+            .fetch()
+            .await
+            .unwrap();
+
+        let err = dir.load_existing_account(&pem).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::AccountNotFound { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_account_from_persisted() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir.register_account(None).await.unwrap();
+
+        let persisted = acc.to_persisted().unwrap();
+        assert!(!format!("{persisted:?}").contains("PRIVATE KEY"));
+
+        let json = serde_json::to_string(&persisted).unwrap();
+        let persisted = serde_json::from_str::<PersistedAccount>(&json).unwrap();
+        assert_eq!(
+            persisted.account_url,
+            server.dir_url.replace("/directory", "/acme/acct/7728515"),
+        );
+
+        let restored = dir.account_from_persisted(persisted).await.unwrap();
+        assert_eq!(restored.thumbprint().unwrap(), acc.thumbprint().unwrap());
+        assert_eq!(
+            restored.to_persisted().unwrap(),
+            acc.to_persisted().unwrap()
+        );
+
+        let mut persisted = acc.to_persisted().unwrap();
+        assert_eq!(
+            persisted.server_key_thumbprint,
+            acc.server_key_thumbprint().unwrap(),
+        );
+
+        let other = acc.thumbprint().unwrap();
+        persisted.server_key_thumbprint = Some(other.clone());
+        let err = dir.account_from_persisted(persisted).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::AccountKeyMismatch { expected, found: Some(_) }) if expected == &other,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_account_with_wrong_key_kind() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let pem = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P384_SHA384)
+            .unwrap()
+            .serialize_pem();
+
+        let err = dir.load_existing_account(&pem).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::KeyKindMismatch { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_terms_of_service_sha256() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let digest = dir.terms_of_service_sha256().await.unwrap().unwrap();
+        assert_eq!(digest, <[u8; 32]>::from(Sha256::digest(crate::test::TERMS)));
+    }
+
+    #[tokio::test]
+    async fn test_sign_new_account() {
+        use base64::prelude::*;
+        use pkcs8::EncodePrivateKey as _;
+
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let key = crate::create_p256_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+        let contact = vec!["mailto:foo@bar.com".to_owned()];
+
+        let jws = dir
+            .sign_new_account(&key, Some(contact), JwsFormat::Flattened)
+            .await
+            .unwrap();
+        let jws = serde_json::from_str::<serde_json::Value>(&jws).unwrap();
+
+        let decode = |part: &serde_json::Value| {
+            let json = BASE64_URL_SAFE_NO_PAD
+                .decode(part.as_str().unwrap())
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap()
+        };
+
+        let protected = decode(&jws["protected"]);
+        assert_eq!(protected["url"], dir.api_directory().new_account);
+        assert_eq!(protected["jwk"]["kty"], "EC");
+        assert!(protected.get("kid").is_none());
+
+        let payload = decode(&jws["payload"]);
+        assert_eq!(payload["contact"][0], "mailto:foo@bar.com");
+        assert_eq!(payload["termsOfServiceAgreed"], true);
+    }
+
+    #[tokio::test]
+    async fn test_sign_new_account_p384() {
+        use base64::prelude::*;
+
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .account_key_kind(KeyKind::EcdsaP384)
+            .fetch()
+            .await
+            .unwrap();
+
+        let key = crate::generate_account_key(KeyKind::EcdsaP384).unwrap();
+
+        let jws = dir
+            .sign_new_account(&key, None, JwsFormat::Compact)
+            .await
+            .unwrap();
+
+        let protected = jws.split('.').next().unwrap();
+        let protected = BASE64_URL_SAFE_NO_PAD.decode(protected).unwrap();
+        let protected = serde_json::from_slice::<serde_json::Value>(&protected).unwrap();
+        assert_eq!(protected["alg"], "ES384");
+        assert_eq!(protected["jwk"]["alg"], "ES384");
+        assert_eq!(protected["jwk"]["crv"], "P-384");
+
+        let signature = BASE64_URL_SAFE_NO_PAD
+            .decode(jws.rsplit('.').next().unwrap())
+            .unwrap();
+        assert_eq!(signature.len(), 96);
+    }
+
+    #[tokio::test]
+    async fn test_create_directory_with_http_client() {
+        let server = crate::test::with_directory_server();
+
+        let client = reqwest::Client::builder()
+            .user_agent("acme-test")
+            .build()
+            .unwrap();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .http_client(client)
+            .fetch()
+            .await
+            .unwrap();
+
+        dir.register_account(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_url() {
+        let server = crate::test::with_directory_server();
+
+        let requested = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url({
+                let requested = Arc::clone(&requested);
+                move |url| {
+                    requested.lock().push(url.to_owned());
+                    url.to_owned()
+                }
+            })
+            .fetch()
+            .await
+            .unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let _ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let api_directory = dir.api_directory();
+        assert_eq!(
+            *requested.lock(),
+            [
+                api_directory.new_nonce.as_str(),
+                api_directory.new_account.as_str(),
+                api_directory.new_order.as_str(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_normalize_contact() {
+        assert_eq!(
+            normalize_contact("foo@bar.com").unwrap(),
+            "mailto:foo@bar.com"
+        );
+        assert_eq!(
+            normalize_contact("mailto:foo@bar.com").unwrap(),
+            "mailto:foo@bar.com"
+        );
+        assert_eq!(
+            normalize_contact("tel:+12025550100").unwrap(),
+            "tel:+12025550100"
+        );
+
+        for contact in [
+            "foo",
+            "@bar.com",
+            "foo@",
+            "foo@bar@baz.com",
+            "foo@bar.com,baz@bar.com",
+        ] {
+            let err = normalize_contact(contact).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::InvalidContact { contact: c }) if c == contact,
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raw_directory() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        assert_eq!(dir.raw()["meta"]["caaIdentities"][0], "testdir.org");
+        assert_eq!(dir.raw()["newOrder"], dir.api_directory().new_order);
+    }
+
+    #[tokio::test]
+    async fn test_directory_meta() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        assert_eq!(
+            dir.terms_of_service().unwrap(),
+            server.dir_url.replace("/directory", "/terms"),
+        );
+        assert_eq!(dir.caa_identities(), ["testdir.org"]);
+        assert_eq!(dir.website(), None);
+        assert!(!dir.external_account_required());
+    }
+
     #[tokio::test]
     async fn test_create_account() {
         let server = crate::test::with_directory_server();
@@ -168,4 +1182,188 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_create_account_api_account() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+
+        let api_account = acc.api_account();
+        assert!(api_account.is_status_valid());
+        assert_eq!(
+            api_account.contact.as_deref(),
+            Some(&["mailto:foo@bar.com".to_owned()][..]),
+        );
+        assert_eq!(api_account.key.as_ref().unwrap()["crv"], "P-256");
+    }
+
+    #[tokio::test]
+    async fn test_register_account_agreeing() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+
+        let Err(err) = dir.register_account_agreeing(None, false).await else {
+            panic!("registration should require agreeing to the terms of service");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::TermsOfServiceNotAgreed {
+                terms_of_service: Some(url),
+            }) if url.ends_with("/terms"),
+        ));
+
+        dir.register_account_agreeing(None, true).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        // accepts connections but never responds
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dir_url = format!("http://{}/directory", lst.local_addr().unwrap());
+
+        let url = DirectoryUrl::Other(&dir_url);
+        let Err(err) = Directory::builder(url)
+            .request_timeout(Duration::from_millis(100))
+            .fetch()
+            .await
+        else {
+            panic!("fetching the directory should time out");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Timeout { url }) if url == &dir_url,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bad_nonce_retry() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-acct", "/acme/new-acct-bad-nonce-once"))
+            .fetch()
+            .await
+            .unwrap();
+
+        dir.register_account(None).await.unwrap();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-acct", "/acme/new-acct-bad-nonce"))
+            .max_bad_nonce_retries(2)
+            .fetch()
+            .await
+            .unwrap();
+
+        let Err(err) = dir.register_account(None).await else {
+            panic!("registration should fail after retrying");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Problem { problem, .. }) if problem.is_bad_nonce(),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_retry() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-acct", "/acme/new-acct-rate-limited-once"))
+            .rate_limit_retry(RateLimitRetry::new(1))
+            .fetch()
+            .await
+            .unwrap();
+
+        dir.register_account(None).await.unwrap();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/new-acct", "/acme/new-acct-rate-limited"))
+            .rate_limit_retry(RateLimitRetry::new(2))
+            .fetch()
+            .await
+            .unwrap();
+
+        let Err(err) = dir.register_account(None).await else {
+            panic!("registration should fail after retrying");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Problem { problem, .. }) if problem.is_rate_limited(),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_retry_then_bad_nonce() {
+        let server = crate::test::with_directory_server();
+
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| {
+                url.replace(
+                    "/acme/new-acct",
+                    "/acme/new-acct-rate-limited-then-bad-nonce",
+                )
+            })
+            .rate_limit_retry(RateLimitRetry::new(3))
+            .max_bad_nonce_retries(1)
+            .fetch()
+            .await
+            .unwrap();
+
+        // rate limit retries do not use up the bad nonce retries
+        dir.register_account(None).await.unwrap();
+    }
+
+    /// Transport answering requests for a directory in-process.
+    struct InProcessTransport;
+
+    impl HttpTransport for InProcessTransport {
+        fn execute(&self, request: reqwest::Request) -> crate::HttpFuture<'_> {
+            Box::pin(async move {
+                let res = match request.url().path() {
+                    "/directory" => http::Response::builder().status(200).body(
+                        r#"{
+                        "newNonce": "https://acme.test/new-nonce",
+                        "newAccount": "https://acme.test/new-acct",
+                        "newOrder": "https://acme.test/new-order",
+                        "revokeCert": "https://acme.test/revoke-cert",
+                        "keyChange": "https://acme.test/key-change"
+                        }"#,
+                    ),
+                    _ => http::Response::builder().status(404).body(""),
+                };
+
+                Ok(res?.into())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_directory_with_http_transport() {
+        let dir = Directory::builder(DirectoryUrl::Other("https://acme.test/directory"))
+            .http_transport(InProcessTransport)
+            .fetch()
+            .await
+            .unwrap();
+
+        assert_eq!(dir.api_directory().new_order, "https://acme.test/new-order",);
+
+        let Err(err) = dir.register_account(None).await else {
+            panic!("newNonce is not served");
+        };
+        assert!(err.to_string().contains("Replay-Nonce"));
+    }
 }
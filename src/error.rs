@@ -1,7 +1,672 @@
-use crate::api::Problem;
+use std::{fmt, time::Duration};
+
+use serde::{ser::SerializeStruct as _, Serialize, Serializer};
+
+use crate::{
+    api::{AuthorizationStatus, Problem},
+    order::ChallengeKind,
+    KeyKind,
+};
 
 impl From<Problem> for eyre::Error {
     fn from(err: Problem) -> eyre::Error {
         eyre::eyre!("{err}")
     }
 }
+
+/// Errors with a specific cause that callers may want to handle.
+///
+/// These are returned wrapped in an [`eyre::Report`]; use [`eyre::Report::downcast_ref()`] to
+/// inspect them.
+///
+/// # Serialization
+///
+/// Errors serialize to a stable, machine-readable form with the fields:
+///
+/// - `kind`: a stable name for the enum variant, e.g., `"problem"`;
+/// - `acme_type`: the ACME problem type, if any;
+/// - `http_status`: the HTTP status code of the response, if any;
+/// - `url`: the URL of the request, if any;
+/// - `detail`: a human-readable description.
+///
+/// ```json
+/// {
+///   "kind": "problem",
+///   "acme_type": "urn:ietf:params:acme:error:rateLimited",
+///   "http_status": 429,
+///   "url": "https://example.com/acme/new-order",
+///   "detail": "Too many new orders recently"
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The ACME API responded with a problem document.
+    Problem {
+        /// URL of the request.
+        url: String,
+
+        /// Problem returned by the ACME API.
+        problem: Problem,
+    },
+
+    /// Solving a challenge failed and so did cleaning up the published proof.
+    Cleanup {
+        /// The original error.
+        error: eyre::Report,
+
+        /// The error returned while cleaning up.
+        cleanup: eyre::Report,
+    },
+
+    /// Polling gave up before the status changed.
+    ///
+    /// See [`PollConfig::with_max_attempts()`].
+    ///
+    /// [`PollConfig::with_max_attempts()`]: crate::PollConfig::with_max_attempts()
+    PollAttemptsExhausted {
+        /// URL of the polled resource.
+        url: String,
+
+        /// Number of attempts made.
+        attempts: u32,
+    },
+
+    /// Polling gave up because the time limit was reached before the status changed.
+    ///
+    /// See [`Challenge::validate_with_timeout()`].
+    ///
+    /// [`Challenge::validate_with_timeout()`]: crate::order::Challenge::validate_with_timeout()
+    PollTimeout {
+        /// URL of the polled resource.
+        url: String,
+
+        /// Number of attempts made.
+        attempts: u32,
+
+        /// Last observed status of the authorization.
+        status: AuthorizationStatus,
+    },
+
+    /// The order was still "processing" when the time limit for finalizing it was reached.
+    ///
+    /// See [`CsrOrder::finalize()`].
+    ///
+    /// [`CsrOrder::finalize()`]: crate::order::CsrOrder::finalize()
+    FinalizeTimeout {
+        /// URL of the order.
+        url: String,
+
+        /// Number of attempts made.
+        attempts: u32,
+    },
+
+    /// The ACME API failed to validate a challenge.
+    ///
+    /// For orders with multiple identifiers, the problem's `subproblems` identify which
+    /// identifiers failed and why.
+    ///
+    /// See [`Challenge::validate()`].
+    ///
+    /// [`Challenge::validate()`]: crate::order::Challenge::validate()
+    ValidationFailed {
+        /// URL of the challenge.
+        url: String,
+
+        /// The challenge's error, if reported by the ACME API.
+        problem: Option<Problem>,
+    },
+
+    /// A private key uses an algorithm not supported by this library.
+    UnsupportedKey {
+        /// Human-readable name of the key's algorithm, e.g., `"RSA"`.
+        algorithm: String,
+    },
+
+    /// A private key is of a different kind than expected.
+    ///
+    /// See [`DirectoryBuilder::account_key_kind()`].
+    ///
+    /// [`DirectoryBuilder::account_key_kind()`]: crate::DirectoryBuilder::account_key_kind()
+    KeyKindMismatch {
+        /// The expected kind of key.
+        expected: KeyKind,
+
+        /// Human-readable name of the key's actual algorithm, e.g., `"ECDSA P-384"`.
+        found: String,
+    },
+
+    /// Some authorizations of an order still need a challenge to be solved.
+    ///
+    /// See [`NewOrder::try_finalize_if_ready()`].
+    ///
+    /// [`NewOrder::try_finalize_if_ready()`]: crate::order::NewOrder::try_finalize_if_ready()
+    ChallengesRequired {
+        /// Domain names of the authorizations that need a challenge.
+        domains: Vec<String>,
+    },
+
+    /// An order expires too soon to be finalized.
+    ///
+    /// See [`CsrOrder::finalize_with_margin()`].
+    ///
+    /// [`CsrOrder::finalize_with_margin()`]: crate::order::CsrOrder::finalize_with_margin()
+    OrderExpiring {
+        /// URL of the order.
+        url: String,
+
+        /// Expiry time of the order, in RFC 3339 format.
+        expires: String,
+    },
+
+    /// An order is invalid, either when resumed or because issuance failed after finalizing it.
+    ///
+    /// See [`Account::order_from_url()`] and [`CsrOrder::finalize_and_wait()`].
+    ///
+    /// [`Account::order_from_url()`]: crate::Account::order_from_url()
+    /// [`CsrOrder::finalize_and_wait()`]: crate::order::CsrOrder::finalize_and_wait()
+    OrderInvalid {
+        /// URL of the order.
+        url: String,
+
+        /// The error that made the order invalid, if reported by the ACME API.
+        problem: Option<Problem>,
+    },
+
+    /// A resumed order expired before it could be finalized.
+    ///
+    /// See [`Account::order_from_url()`].
+    ///
+    /// [`Account::order_from_url()`]: crate::Account::order_from_url()
+    OrderExpired {
+        /// URL of the order.
+        url: String,
+
+        /// Expiry time of the order, in RFC 3339 format.
+        expires: String,
+    },
+
+    /// A valid order's certificate could still not be downloaded after retrying.
+    ///
+    /// See [`CertOrder::download_cert_with_retry()`].
+    ///
+    /// [`CertOrder::download_cert_with_retry()`]: crate::order::CertOrder::download_cert_with_retry()
+    CertificateUnavailable {
+        /// URL of the certificate.
+        url: String,
+
+        /// Number of attempts made.
+        attempts: u32,
+    },
+
+    /// The ACME API provider does not offer the required type of challenge.
+    ///
+    /// See [`Auth::require_challenge()`].
+    ///
+    /// [`Auth::require_challenge()`]: crate::order::Auth::require_challenge()
+    ChallengeTypeUnavailable {
+        /// The required type of challenge.
+        kind: ChallengeKind,
+
+        /// Types of challenge that are offered, e.g., `"http-01"`.
+        available: Vec<String>,
+    },
+
+    /// The account key reported by the ACME API does not match the pinned thumbprint.
+    ///
+    /// See [`Account::verify_server_key()`].
+    ///
+    /// [`Account::verify_server_key()`]: crate::Account::verify_server_key()
+    AccountKeyMismatch {
+        /// The pinned thumbprint.
+        expected: String,
+
+        /// Thumbprint of the reported account key, if the ACME API reported one.
+        found: Option<String>,
+    },
+
+    /// The newNonce endpoint responded successfully but without a `Replay-Nonce` header.
+    ///
+    /// This indicates that the ACME API provider's newNonce endpoint is misconfigured. Both `HEAD`
+    /// and `GET` requests are attempted before this is returned.
+    MissingNonceHeader {
+        /// URL of the newNonce endpoint.
+        url: String,
+
+        /// HTTP status code of the last response.
+        status: u16,
+    },
+
+    /// A domain name contains non-ASCII characters but is not a valid internationalized domain
+    /// name, so it cannot be converted to its ASCII form.
+    ///
+    /// See [`util::domain_to_ascii()`](crate::util::domain_to_ascii()).
+    InvalidDomainName {
+        /// The domain name, as given.
+        domain: String,
+    },
+
+    /// An account contact is not a valid email address.
+    InvalidContact {
+        /// The invalid contact.
+        contact: String,
+    },
+
+    /// The ACME API has no account for the given key.
+    ///
+    /// See [`Directory::load_existing_account()`].
+    ///
+    /// [`Directory::load_existing_account()`]: crate::Directory::load_existing_account()
+    AccountNotFound {
+        /// URL of the newAccount endpoint.
+        url: String,
+    },
+
+    /// Fetching a directory and registering an account did not complete in time.
+    ///
+    /// See [`Directory::from_url_with_timeout()`].
+    ///
+    /// [`Directory::from_url_with_timeout()`]: crate::Directory::from_url_with_timeout()
+    BootstrapTimeout {
+        /// URL of the directory.
+        url: String,
+
+        /// The time limit that was exceeded.
+        timeout: Duration,
+    },
+
+    /// The identifiers requested by a CSR do not match those of the order.
+    ///
+    /// See [`CsrOrder::finalize_with_der_csr()`].
+    ///
+    /// [`CsrOrder::finalize_with_der_csr()`]: crate::order::CsrOrder::finalize_with_der_csr()
+    CsrIdentifiersMismatch {
+        /// Identifiers of the order that the CSR does not request.
+        missing: Vec<String>,
+
+        /// Identifiers requested by the CSR that are not in the order.
+        unexpected: Vec<String>,
+    },
+
+    /// A challenge's proof could not be verified before validation.
+    ///
+    /// See [`Challenge::self_check()`].
+    ///
+    /// [`Challenge::self_check()`]: crate::order::Challenge::self_check()
+    SelfCheckFailed {
+        /// URL where the proof was expected.
+        url: String,
+
+        /// Why the proof could not be verified.
+        reason: String,
+    },
+
+    /// A `dns-01` challenge's proof was not found in DNS before validation.
+    ///
+    /// See the `self_check()` method of [`Challenge<Dns>`].
+    ///
+    /// [`Challenge<Dns>`]: crate::order::Challenge
+    DnsSelfCheckFailed {
+        /// Name of the `TXT` record, without a trailing dot.
+        record_name: String,
+
+        /// Values of the `TXT` records that were found.
+        found: Vec<String>,
+    },
+
+    /// An HTTP request to the ACME API did not complete in time.
+    ///
+    /// See [`DirectoryBuilder::request_timeout()`].
+    ///
+    /// [`DirectoryBuilder::request_timeout()`]: crate::DirectoryBuilder::request_timeout()
+    Timeout {
+        /// URL of the request.
+        url: String,
+    },
+
+    /// The ACME API provider requires agreeing to its terms of service but the caller did not.
+    ///
+    /// See [`Directory::register_account_agreeing()`].
+    ///
+    /// [`Directory::register_account_agreeing()`]: crate::Directory::register_account_agreeing()
+    TermsOfServiceNotAgreed {
+        /// URL of the terms of service, if advertised.
+        terms_of_service: Option<String>,
+    },
+
+    /// The certificate's private key is not known because the order was finalized with an
+    /// external CSR.
+    ///
+    /// See [`CsrOrder::finalize_with_der_csr()`].
+    ///
+    /// [`CsrOrder::finalize_with_der_csr()`]: crate::order::CsrOrder::finalize_with_der_csr()
+    PrivateKeyUnknown {
+        /// URL of the order.
+        url: String,
+    },
+
+    /// A seed is not a valid P-256 private key, i.e., it is zero or not less than the curve order.
+    ///
+    /// See [`create_p256_key_from_seed()`].
+    ///
+    /// [`create_p256_key_from_seed()`]: crate::create_p256_key_from_seed()
+    InvalidKeySeed,
+
+    /// Following `CNAME` records from a `dns-01` challenge's record name did not end.
+    ///
+    /// See [`Challenge<Dns>::delegated_record_name()`].
+    ///
+    /// [`Challenge<Dns>::delegated_record_name()`]: crate::order::Challenge::delegated_record_name()
+    CnameChainTooLong {
+        /// Name of the `TXT` record the chain starts at, without a trailing dot.
+        record_name: String,
+    },
+}
+
+impl Error {
+    /// Returns a stable name for the kind of error.
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Problem { .. } => "problem",
+            Error::Cleanup { .. } => "cleanup",
+            Error::PollAttemptsExhausted { .. } => "poll_attempts_exhausted",
+            Error::PollTimeout { .. } => "poll_timeout",
+            Error::FinalizeTimeout { .. } => "finalize_timeout",
+            Error::ValidationFailed { .. } => "validation_failed",
+            Error::UnsupportedKey { .. } => "unsupported_key",
+            Error::KeyKindMismatch { .. } => "key_kind_mismatch",
+            Error::ChallengesRequired { .. } => "challenges_required",
+            Error::OrderExpiring { .. } => "order_expiring",
+            Error::OrderInvalid { .. } => "order_invalid",
+            Error::OrderExpired { .. } => "order_expired",
+            Error::CertificateUnavailable { .. } => "certificate_unavailable",
+            Error::ChallengeTypeUnavailable { .. } => "challenge_type_unavailable",
+            Error::AccountKeyMismatch { .. } => "account_key_mismatch",
+            Error::MissingNonceHeader { .. } => "missing_nonce_header",
+            Error::InvalidDomainName { .. } => "invalid_domain_name",
+            Error::InvalidContact { .. } => "invalid_contact",
+            Error::AccountNotFound { .. } => "account_not_found",
+            Error::BootstrapTimeout { .. } => "bootstrap_timeout",
+            Error::CsrIdentifiersMismatch { .. } => "csr_identifiers_mismatch",
+            Error::SelfCheckFailed { .. } => "self_check_failed",
+            Error::DnsSelfCheckFailed { .. } => "dns_self_check_failed",
+            Error::Timeout { .. } => "timeout",
+            Error::TermsOfServiceNotAgreed { .. } => "terms_of_service_not_agreed",
+            Error::CnameChainTooLong { .. } => "cname_chain_too_long",
+            Error::PrivateKeyUnknown { .. } => "private_key_unknown",
+            Error::InvalidKeySeed => "invalid_key_seed",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Problem { problem, .. } => write!(f, "{problem}"),
+            Error::Cleanup { error, cleanup } => {
+                write!(f, "{error} (additionally, cleanup failed: {cleanup})")
+            }
+            Error::PollAttemptsExhausted { url, attempts } => {
+                write!(
+                    f,
+                    "Status of {url} did not change after {attempts} attempts"
+                )
+            }
+            Error::PollTimeout {
+                url,
+                attempts,
+                status,
+            } => {
+                write!(
+                    f,
+                    "Status of {url} was still {status:?} after {attempts} attempts when time ran out"
+                )
+            }
+            Error::FinalizeTimeout { url, attempts } => {
+                write!(
+                    f,
+                    "Order {url} was still processing after {attempts} attempts when time ran out"
+                )
+            }
+            Error::ValidationFailed {
+                problem: Some(problem),
+                ..
+            } => {
+                write!(f, "Validation failed: {problem}")?;
+
+                for subproblem in problem.subproblems.iter().flatten() {
+                    write!(f, "; {subproblem}")?;
+                }
+
+                Ok(())
+            }
+            Error::ValidationFailed { problem: None, .. } => {
+                write!(f, "Validation failed: Validation failed and no error found")
+            }
+            Error::UnsupportedKey { algorithm } => {
+                write!(f, "Unsupported key algorithm: {algorithm}")
+            }
+            Error::KeyKindMismatch { expected, found } => {
+                write!(f, "Expected {expected} key but found {found} key")
+            }
+            Error::ChallengesRequired { domains } => {
+                write!(f, "Challenges required for: {}", domains.join(", "))
+            }
+            Error::OrderExpiring { url, expires } => {
+                write!(f, "Order {url} expires too soon to finalize (at {expires})")
+            }
+            Error::OrderInvalid {
+                url,
+                problem: Some(problem),
+            } => {
+                write!(f, "Order {url} is invalid: {problem}")
+            }
+            Error::OrderInvalid { url, problem: None } => {
+                write!(f, "Order {url} is invalid")
+            }
+            Error::OrderExpired { url, expires } => {
+                write!(f, "Order {url} expired at {expires}")
+            }
+            Error::CertificateUnavailable { url, attempts } => {
+                write!(
+                    f,
+                    "Certificate {url} was not available after {attempts} attempts"
+                )
+            }
+            Error::ChallengeTypeUnavailable { kind, available } => {
+                write!(
+                    f,
+                    "No {kind} challenge offered (available: {})",
+                    available.join(", ")
+                )
+            }
+            Error::AccountKeyMismatch {
+                expected,
+                found: Some(found),
+            } => {
+                write!(
+                    f,
+                    "Expected account key with thumbprint {expected} but found {found}"
+                )
+            }
+            Error::AccountKeyMismatch {
+                expected,
+                found: None,
+            } => {
+                write!(
+                    f,
+                    "Expected account key with thumbprint {expected} but none was reported"
+                )
+            }
+            Error::MissingNonceHeader { url, status } => {
+                write!(
+                    f,
+                    "No Replay-Nonce header in {status} response from newNonce endpoint {url}"
+                )
+            }
+            Error::InvalidDomainName { domain } => {
+                write!(f, "Invalid internationalized domain name: {domain}")
+            }
+            Error::InvalidContact { contact } => {
+                write!(f, "Invalid account contact: {contact}")
+            }
+            Error::AccountNotFound { .. } => {
+                write!(f, "No account exists for the given key")
+            }
+            Error::BootstrapTimeout { url, timeout } => {
+                write!(
+                    f,
+                    "Bootstrapping from directory {url} did not complete within {timeout:?}"
+                )
+            }
+            Error::CsrIdentifiersMismatch {
+                missing,
+                unexpected,
+            } => {
+                write!(
+                    f,
+                    "CSR identifiers do not match order (missing: {}; unexpected: {})",
+                    missing.join(", "),
+                    unexpected.join(", "),
+                )
+            }
+            Error::SelfCheckFailed { url, reason } => {
+                write!(f, "Self-check of {url} failed: {reason}")
+            }
+            Error::DnsSelfCheckFailed { record_name, found } => {
+                write!(
+                    f,
+                    "Proof not found in TXT records for {record_name} (found: {found:?})"
+                )
+            }
+            Error::Timeout { url } => {
+                write!(f, "Request to {url} timed out")
+            }
+            Error::TermsOfServiceNotAgreed {
+                terms_of_service: Some(url),
+            } => {
+                write!(f, "Terms of service at {url} must be agreed to")
+            }
+            Error::TermsOfServiceNotAgreed {
+                terms_of_service: None,
+            } => {
+                write!(f, "Terms of service must be agreed to")
+            }
+            Error::CnameChainTooLong { record_name } => {
+                write!(f, "Too many CNAME records to follow from {record_name}")
+            }
+            Error::InvalidKeySeed => {
+                write!(f, "Seed is not a valid P-256 private key")
+            }
+            Error::PrivateKeyUnknown { url } => {
+                write!(
+                    f,
+                    "Private key of order {url} is unknown (finalized with an external CSR)"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Cleanup { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (acme_type, http_status, url, detail) = match self {
+            Error::Problem { url, problem } => (
+                Some(problem._type.as_str()),
+                problem.status,
+                Some(url.as_str()),
+                problem.detail.clone(),
+            ),
+            Error::ValidationFailed {
+                url,
+                problem: Some(problem),
+            } => (
+                Some(problem._type.as_str()),
+                problem.status,
+                Some(url.as_str()),
+                Some(self.to_string()),
+            ),
+            Error::MissingNonceHeader { url, status } => (
+                None,
+                Some(*status),
+                Some(url.as_str()),
+                Some(self.to_string()),
+            ),
+            Error::PollAttemptsExhausted { url, .. }
+            | Error::PollTimeout { url, .. }
+            | Error::FinalizeTimeout { url, .. }
+            | Error::ValidationFailed { url, .. }
+            | Error::OrderExpiring { url, .. }
+            | Error::OrderInvalid { url, .. }
+            | Error::OrderExpired { url, .. }
+            | Error::CertificateUnavailable { url, .. }
+            | Error::AccountNotFound { url }
+            | Error::BootstrapTimeout { url, .. }
+            | Error::PrivateKeyUnknown { url }
+            | Error::Timeout { url } => (None, None, Some(url.as_str()), Some(self.to_string())),
+            _ => (None, None, None, Some(self.to_string())),
+        };
+
+        let mut state = serializer.serialize_struct("Error", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("acme_type", &acme_type)?;
+        state.serialize_field("http_status", &http_status)?;
+        state.serialize_field("url", &url)?;
+        state.serialize_field("detail", &detail)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_error() {
+        let err = Error::Problem {
+            url: "https://example.com/acme/new-order".to_owned(),
+            problem: Problem {
+                _type: "urn:ietf:params:acme:error:rateLimited".to_owned(),
+                detail: Some("Too many new orders recently".to_owned()),
+                status: Some(429),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "problem",
+                "acme_type": "urn:ietf:params:acme:error:rateLimited",
+                "http_status": 429,
+                "url": "https://example.com/acme/new-order",
+                "detail": "Too many new orders recently",
+            }),
+        );
+
+        let err = Error::Cleanup {
+            error: eyre::eyre!("validation failed"),
+            cleanup: eyre::eyre!("remove failed"),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "cleanup",
+                "acme_type": null,
+                "http_status": null,
+                "url": null,
+                "detail": "validation failed (additionally, cleanup failed: remove failed)",
+            }),
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+/// Future returned by [`HttpTransport::execute()`].
+pub type HttpFuture<'a> =
+    Pin<Box<dyn Future<Output = eyre::Result<reqwest::Response>> + Send + 'a>>;
+
+/// Sends HTTP requests to the ACME API.
+///
+/// Implemented for [`reqwest::Client`], which is used by default. Custom implementations can be
+/// set using [`DirectoryBuilder::http_transport()`], e.g., for answering requests in-process in
+/// tests or for alternative HTTP backends. Responses can be constructed from [`http::Response`]s
+/// using [`reqwest::Response::from()`].
+///
+/// ```
+/// use acme::{HttpFuture, HttpTransport};
+///
+/// struct LoggingTransport(reqwest::Client);
+///
+/// impl HttpTransport for LoggingTransport {
+///     fn execute(&self, request: reqwest::Request) -> HttpFuture<'_> {
+///         println!("{} {}", request.method(), request.url());
+///         HttpTransport::execute(&self.0, request)
+///     }
+/// }
+/// ```
+///
+/// [`DirectoryBuilder::http_transport()`]: crate::DirectoryBuilder::http_transport()
+/// [`http::Response`]: https://docs.rs/http/1/http/response/struct.Response.html
+pub trait HttpTransport: Send + Sync {
+    /// Sends `request` and returns the response.
+    ///
+    /// Responses with error statuses must be returned as responses, not errors.
+    fn execute(&self, request: reqwest::Request) -> HttpFuture<'_>;
+}
+
+impl HttpTransport for reqwest::Client {
+    fn execute(&self, request: reqwest::Request) -> HttpFuture<'_> {
+        Box::pin(async move { Ok(reqwest::Client::execute(self, request).await?) })
+    }
+}
+
+/// Shared HTTP transport.
+#[derive(Clone)]
+pub(crate) struct HttpClient(Arc<dyn HttpTransport>);
+
+impl HttpClient {
+    pub fn new(transport: impl HttpTransport + 'static) -> Self {
+        HttpClient(Arc::new(transport))
+    }
+
+    pub async fn execute(&self, request: reqwest::Request) -> eyre::Result<reqwest::Response> {
+        self.0.execute(request).await
+    }
+}
+
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HttpClient(..)")
+    }
+}
@@ -1,7 +1,10 @@
 //! See [RFC 8555 §6.2](https://datatracker.ietf.org/doc/html/rfc8555#section-6.2).
 
+use std::collections::BTreeMap;
+
 use base64::prelude::*;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 
 use crate::acc::AcmeKey;
 
@@ -37,7 +40,10 @@ pub(crate) struct JwsProtectedHeader {
     /// > to this encoding, then the verifier MUST reject the JWS as malformed
     ///
     /// [RFC 8555 §6.5: https://datatracker.ietf.org/doc/html/rfc8555#section-6.5.
-    nonce: String,
+    ///
+    /// Absent only in the inner JWS of keyChange requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
 
     /// Defined in [RFC 8555 §6.4].
     ///
@@ -66,19 +72,31 @@ impl JwsProtectedHeader {
 
     pub(crate) fn new_jwk(jwk: Jwk, url: &str, nonce: String) -> Self {
         JwsProtectedHeader {
-            alg: "ES256".to_owned(),
+            alg: jwk.alg.clone(),
             url: url.to_owned(),
-            nonce,
+            nonce: Some(nonce),
             jwk: Some(jwk),
             ..Default::default()
         }
     }
 
-    pub(crate) fn new_kid(kid: &str, url: &str, nonce: String) -> Self {
+    /// Header of the inner JWS of a keyChange request, as defined in [RFC 8555 §7.3.5].
+    ///
+    /// [RFC 8555 §7.3.5]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5
+    pub(crate) fn new_key_change(jwk: Jwk, url: &str) -> Self {
         JwsProtectedHeader {
-            alg: "ES256".to_owned(),
+            alg: jwk.alg.clone(),
             url: url.to_owned(),
-            nonce,
+            jwk: Some(jwk),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn new_kid(kid: &str, alg: &str, url: &str, nonce: String) -> Self {
+        JwsProtectedHeader {
+            alg: alg.to_owned(),
+            url: url.to_owned(),
+            nonce: Some(nonce),
             kid: Some(kid.to_owned()),
             ..Default::default()
         }
@@ -100,15 +118,12 @@ impl TryFrom<&AcmeKey> for Jwk {
     type Error = eyre::Error;
 
     fn try_from(a: &AcmeKey) -> eyre::Result<Self> {
-        let point = a.private_key().verifying_key().to_encoded_point(false);
-
-        let x = point.x().unwrap();
-        let y = point.y().unwrap();
+        let (crv, x, y) = a.public_coordinates();
 
         Ok(Jwk {
-            alg: "ES256".to_owned(),
+            alg: a.alg().to_owned(),
             kty: "EC".to_owned(),
-            crv: "P-256".to_owned(),
+            crv: crv.to_owned(),
             _use: "sig".to_owned(),
             x: BASE64_URL_SAFE_NO_PAD.encode(x),
             y: BASE64_URL_SAFE_NO_PAD.encode(y),
@@ -136,6 +151,66 @@ impl From<&Jwk> for JwkThumb {
     }
 }
 
+/// Computes the base64url-encoded JWK thumbprint of `key`, as defined in [RFC 7638].
+///
+/// [RFC 7638]: https://datatracker.ietf.org/doc/html/rfc7638
+pub(crate) fn jwk_thumbprint(key: &AcmeKey) -> eyre::Result<String> {
+    let jwk = Jwk::try_from(key)?;
+    let jwk_thumb = JwkThumb::from(&jwk);
+    let jwk_json = serde_json::to_string(&jwk_thumb)?;
+
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(jwk_json)))
+}
+
+/// Computes the base64url-encoded thumbprint of the public JWK `jwk`, as defined in [RFC 7638].
+///
+/// Only the members required by [RFC 7638 §3.2] for the key type are hashed.
+///
+/// [RFC 7638]: https://datatracker.ietf.org/doc/html/rfc7638
+/// [RFC 7638 §3.2]: https://datatracker.ietf.org/doc/html/rfc7638#section-3.2
+pub(crate) fn jwk_value_thumbprint(jwk: &serde_json::Value) -> eyre::Result<String> {
+    let members: &[&str] = match jwk["kty"].as_str() {
+        Some("EC") => &["crv", "kty", "x", "y"],
+        Some("OKP") => &["crv", "kty", "x"],
+        Some("RSA") => &["e", "kty", "n"],
+        kty => return Err(eyre::eyre!("Unsupported JWK key type: {kty:?}")),
+    };
+
+    // BTreeMap serializes in lexical order, as required
+    let required = members
+        .iter()
+        .map(|&name| {
+            let value = jwk[name]
+                .as_str()
+                .ok_or_else(|| eyre::eyre!("JWK is missing \"{name}\" member"))?;
+            Ok((name, value))
+        })
+        .collect::<eyre::Result<BTreeMap<_, _>>>()?;
+
+    let jwk_json = serde_json::to_string(&required)?;
+
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(jwk_json)))
+}
+
+/// Serialization format of a signed JWS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JwsFormat {
+    /// Flattened JSON serialization, as defined in [RFC 7515 §7.2.2].
+    ///
+    /// This is the format required by ACME.
+    ///
+    /// [RFC 7515 §7.2.2]: https://datatracker.ietf.org/doc/html/rfc7515#section-7.2.2
+    #[default]
+    Flattened,
+
+    /// Compact serialization, as defined in [RFC 7515 §7.1].
+    ///
+    /// Not accepted by ACME API providers but useful for verifying requests with other tools.
+    ///
+    /// [RFC 7515 §7.1]: https://datatracker.ietf.org/doc/html/rfc7515#section-7.1
+    Compact,
+}
+
 /// <https://datatracker.ietf.org/doc/html/rfc7515#section-7.2.2>
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct FlattenedJsonJws {
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use der::{asn1::ObjectIdentifier, oid::db::rfc5912};
+use eyre::WrapErr as _;
+
+use crate::Error;
+
+/// Kind of private key, i.e., its algorithm and parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KeyKind {
+    /// ECDSA using the P-256 curve (i.e., `ES256`).
+    #[default]
+    EcdsaP256,
+
+    /// ECDSA using the P-384 curve (i.e., `ES384`).
+    ///
+    /// Only supported for account keys.
+    EcdsaP384,
+}
+
+impl KeyKind {
+    /// Returns the kind of the PEM-encoded (PKCS #8) private key `pem`.
+    ///
+    /// Fails with [`Error::UnsupportedKey`] if the key is of a kind not supported by this library.
+    pub fn of_pem(pem: &str) -> eyre::Result<Self> {
+        detect_pem(pem)?.map_err(|algorithm| Error::UnsupportedKey { algorithm }.into())
+    }
+
+    /// Checks that the PEM-encoded (PKCS #8) private key `pem` is of this kind.
+    ///
+    /// Fails with [`Error::KeyKindMismatch`] if it is not.
+    pub(crate) fn check_pem(self, pem: &str) -> eyre::Result<()> {
+        let found = match detect_pem(pem)? {
+            Ok(kind) if kind == self => return Ok(()),
+            Ok(kind) => kind.to_string(),
+            Err(algorithm) => algorithm,
+        };
+
+        Err(Error::KeyKindMismatch {
+            expected: self,
+            found,
+        }
+        .into())
+    }
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyKind::EcdsaP256 => f.write_str("ECDSA P-256"),
+            KeyKind::EcdsaP384 => f.write_str("ECDSA P-384"),
+        }
+    }
+}
+
+/// Returns the kind of the PEM-encoded (PKCS #8) private key `pem`, or a human-readable name for
+/// its algorithm if it is not supported.
+fn detect_pem(pem: &str) -> eyre::Result<Result<KeyKind, String>> {
+    let (_label, doc) = pkcs8::SecretDocument::from_pem(pem).context("Failed to read PEM")?;
+    let info = pkcs8::PrivateKeyInfo::try_from(doc.as_bytes())
+        .context("Failed to read PKCS #8 private key")?;
+
+    let algorithm = info.algorithm.oid;
+    let parameters = info.algorithm.parameters_oid().ok();
+
+    Ok(match (algorithm, parameters) {
+        (rfc5912::ID_EC_PUBLIC_KEY, Some(rfc5912::SECP_256_R_1)) => Ok(KeyKind::EcdsaP256),
+        (rfc5912::ID_EC_PUBLIC_KEY, Some(rfc5912::SECP_384_R_1)) => Ok(KeyKind::EcdsaP384),
+        _ => Err(describe_algorithm(algorithm, parameters)),
+    })
+}
+
+/// Returns a human-readable name for an unsupported private key algorithm.
+fn describe_algorithm(algorithm: ObjectIdentifier, parameters: Option<ObjectIdentifier>) -> String {
+    const ID_ED_25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+    match (algorithm, parameters) {
+        (rfc5912::ID_EC_PUBLIC_KEY, Some(rfc5912::SECP_521_R_1)) => "ECDSA P-521".to_owned(),
+        (rfc5912::RSA_ENCRYPTION, _) => "RSA".to_owned(),
+        (ID_ED_25519, _) => "Ed25519".to_owned(),
+        (algorithm, Some(parameters)) => format!("{algorithm} ({parameters})"),
+        (algorithm, None) => algorithm.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pkcs8::EncodePrivateKey as _;
+
+    use super::*;
+
+    #[test]
+    fn test_key_kind_of_pem() {
+        let p256 = crate::create_p256_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+        assert_eq!(KeyKind::of_pem(&p256).unwrap(), KeyKind::EcdsaP256);
+        KeyKind::EcdsaP256.check_pem(&p256).unwrap();
+
+        let p384 = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P384_SHA384)
+            .unwrap()
+            .serialize_pem();
+        let err = KeyKind::EcdsaP256.check_pem(&p384).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::KeyKindMismatch { expected: KeyKind::EcdsaP256, found }) if found == "ECDSA P-384",
+        ));
+        assert_eq!(KeyKind::of_pem(&p384).unwrap(), KeyKind::EcdsaP384);
+
+        let ed25519 = rcgen::KeyPair::generate_for(&rcgen::PKCS_ED25519)
+            .unwrap()
+            .serialize_pem();
+        let err = KeyKind::of_pem(&ed25519).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported key algorithm: Ed25519");
+    }
+}
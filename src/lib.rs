@@ -56,21 +56,40 @@
 #![deny(rust_2018_idioms, nonstandard_style, future_incompatible)]
 
 mod acc;
+mod caa;
 mod cert;
 mod dir;
 mod error;
+mod http;
 mod jws;
+mod key;
+mod poll;
 mod req;
+mod retry;
 mod trans;
 
 pub mod api;
 pub mod order;
+pub mod util;
 
 #[cfg(test)]
 mod test;
 
 pub use crate::{
-    acc::{Account, RevocationReason},
-    cert::{create_p256_key, Certificate},
-    dir::{Directory, DirectoryUrl},
+    acc::{
+        generate_account_key, Account, OrdersPage, PendingOrdersCleanup, PersistedAccount,
+        RevocationReason,
+    },
+    caa::{precheck_caa, CaaLookupFuture, CaaRecord, CaaResolver},
+    cert::{
+        create_p256_key, create_p256_key_from_seed, create_p256_key_with_rng, create_p384_key,
+        CertChain, Certificate, CsrParams,
+    },
+    dir::{Directory, DirectoryBuilder, DirectoryUrl},
+    error::Error,
+    http::{HttpFuture, HttpTransport},
+    jws::JwsFormat,
+    key::KeyKind,
+    poll::PollConfig,
+    retry::RateLimitRetry,
 };
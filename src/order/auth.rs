@@ -1,14 +1,33 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use base64::prelude::*;
 use sha2::{Digest as _, Sha256};
+use zeroize::Zeroizing;
 
 use crate::{
     acc::{AccountInner, AcmeKey},
     api,
-    jws::{Jwk, JwkThumb},
+    cert::create_tls_alpn_certificate,
+    jws::jwk_thumbprint,
+    order::{
+        solver::{with_cleanup, CatchUnwind, DnsSolver, HttpSolver, TxtResolver},
+        ChallengeStore,
+    },
+    req::{req_get_with_host, req_json, req_retry_after},
+    Error, PollConfig,
 };
 
+/// Maximum number of `CNAME` records followed by [`Challenge::delegated_record_name()`].
+const MAX_CNAME_CHAIN_LENGTH: usize = 8;
+
+/// Time limit used by [`Challenge::validate()`].
+const DEFAULT_VALIDATE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
 /// An authorization ([ownership proof]) for a domain name.
 ///
 /// Each authorization for an order much be progressed to a valid state before the ACME API
@@ -90,7 +109,7 @@ impl Auth {
     pub fn http_challenge(&self) -> Option<Challenge<Http>> {
         self.api_auth
             .http_challenge()
-            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url))
+            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url, self.domain_name()))
     }
 
     /// Get the dns challenge.
@@ -110,7 +129,7 @@ impl Auth {
     ///
     /// async fn dns_authorize(auth: &Auth) -> eyre::Result<()> {
     ///   let challenge = auth.dns_challenge().unwrap();
-    ///   let record = format!("_acme-challenge.{}.", auth.domain_name());
+    ///   let record = auth.dns_record();
     ///   // route_53_set_record(&record, "TXT", challenge.dns_proof());
     ///   challenge.validate(Duration::from_millis(5000)).await?;
     ///   Ok(())
@@ -121,7 +140,24 @@ impl Auth {
     pub fn dns_challenge(&self) -> Option<Challenge<Dns>> {
         self.api_auth
             .dns_challenge()
-            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url))
+            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url, self.domain_name()))
+    }
+
+    /// Returns the name of the `TXT` record for the dns challenge, without a trailing dot.
+    ///
+    /// E.g., `_acme-challenge.example.org`. Most DNS provider APIs expect record names in this
+    /// form, and it is the form passed to [`DnsSolver`] implementations. Use
+    /// [`dns_record_fqdn()`](Self::dns_record_fqdn) for APIs and zone files which expect a fully
+    /// qualified name.
+    pub fn dns_record(&self) -> String {
+        dns_record_name(self.domain_name())
+    }
+
+    /// Returns the name of the `TXT` record for the dns challenge, with a trailing dot.
+    ///
+    /// E.g., `_acme-challenge.example.org.`. See [`dns_record()`](Self::dns_record).
+    pub fn dns_record_fqdn(&self) -> String {
+        format!("{}.", self.dns_record())
     }
 
     /// Returns the TLS ALPN challenge.
@@ -133,15 +169,183 @@ impl Auth {
     pub fn tls_alpn_challenge(&self) -> Option<Challenge<TlsAlpn>> {
         self.api_auth
             .tls_alpn_challenge()
-            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url))
+            .map(|c| Challenge::new(&self.inner, c.clone(), &self.auth_url, self.domain_name()))
+    }
+
+    /// Returns the challenge with the ACME API provided `url`.
+    ///
+    /// Useful for resuming work on a specific challenge whose URL was persisted, e.g., by another
+    /// process. Returns `None` if this authorization has no such challenge, or if the challenge is
+    /// of a type not supported by this library.
+    pub fn challenge_by_url(&self, url: &str) -> Option<AnyChallenge> {
+        let c = self.api_auth.challenges.iter().find(|c| c.url == url)?;
+        let kind = ChallengeKind::from_type(&c._type)?;
+        Some(self.any_challenge(kind, c))
+    }
+
+    /// Returns the challenge of type `kind`.
+    ///
+    /// Fails with [`Error::ChallengeTypeUnavailable`], listing the types of challenge that are
+    /// offered, if the ACME API provider does not offer this type of challenge for this
+    /// authorization.
+    ///
+    /// [`Error::ChallengeTypeUnavailable`]: crate::Error::ChallengeTypeUnavailable
+    pub fn require_challenge(&self, kind: ChallengeKind) -> eyre::Result<AnyChallenge> {
+        let challenges = &self.api_auth.challenges;
+
+        match challenges.iter().find(|c| c._type == kind.as_str()) {
+            Some(c) => Ok(self.any_challenge(kind, c)),
+            None => Err(Error::ChallengeTypeUnavailable {
+                kind,
+                available: challenges.iter().map(|c| c._type.clone()).collect(),
+            }
+            .into()),
+        }
+    }
+
+    fn any_challenge(&self, kind: ChallengeKind, c: &api::Challenge) -> AnyChallenge {
+        match kind {
+            ChallengeKind::Http01 => AnyChallenge::Http(Challenge::new(
+                &self.inner,
+                c.clone(),
+                &self.auth_url,
+                self.domain_name(),
+            )),
+            ChallengeKind::Dns01 => AnyChallenge::Dns(Challenge::new(
+                &self.inner,
+                c.clone(),
+                &self.auth_url,
+                self.domain_name(),
+            )),
+            ChallengeKind::TlsAlpn01 => AnyChallenge::TlsAlpn(Challenge::new(
+                &self.inner,
+                c.clone(),
+                &self.auth_url,
+                self.domain_name(),
+            )),
+        }
+    }
+
+    /// Solves the dns challenge using `solver`.
+    ///
+    /// The proof is published using [`DnsSolver::set_record()`], the challenge is validated,
+    /// polling every `delay`, and then the record is removed using [`DnsSolver::remove_record()`].
+    /// The record is removed even if publishing or validation fails or panics; if removing it also
+    /// fails, both errors are returned in an [`Error::Cleanup`].
+    ///
+    /// Does nothing if this authorization does not [need a challenge](Self::need_challenge).
+    ///
+    /// [`Error::Cleanup`]: crate::Error::Cleanup
+    pub async fn solve_dns<S>(&self, solver: &S, delay: Duration) -> eyre::Result<()>
+    where
+        S: DnsSolver + ?Sized,
+    {
+        if !self.need_challenge() {
+            return Ok(());
+        }
+
+        let challenge = self
+            .dns_challenge()
+            .ok_or_else(|| eyre::eyre!("No dns-01 challenge offered for {}", self.domain_name()))?;
+
+        let record_name = self.dns_record();
+        let proof = challenge.dns_proof()?;
+
+        let res = CatchUnwind::new(async {
+            solver.set_record(&record_name, &proof).await?;
+            challenge.validate(delay).await
+        })
+        .await;
+
+        let cleanup = solver.remove_record(&record_name, &proof).await;
+
+        match res {
+            Ok(res) => with_cleanup(res, cleanup),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Solves the http challenge using `solver`.
+    ///
+    /// The proof is published using [`HttpSolver::publish()`], the challenge is validated, polling
+    /// every `delay`, and then the proof is removed using [`HttpSolver::unpublish()`]. The proof is
+    /// removed even if publishing or validation fails or panics; if removing it also fails, both
+    /// errors are returned in an [`Error::Cleanup`].
+    ///
+    /// Does nothing if this authorization does not [need a challenge](Self::need_challenge).
+    ///
+    /// [`Error::Cleanup`]: crate::Error::Cleanup
+    pub async fn solve_http<S>(&self, solver: &S, delay: Duration) -> eyre::Result<()>
+    where
+        S: HttpSolver + ?Sized,
+    {
+        if !self.need_challenge() {
+            return Ok(());
+        }
+
+        let challenge = self.http_challenge().ok_or_else(|| {
+            eyre::eyre!("No http-01 challenge offered for {}", self.domain_name())
+        })?;
+
+        let domain = self.domain_name();
+        let token = challenge.http_token();
+        let proof = challenge.http_proof()?;
+
+        let res = CatchUnwind::new(async {
+            solver.publish(domain, token, &proof).await?;
+            challenge.validate(delay).await
+        })
+        .await;
+
+        let cleanup = solver.unpublish(domain, token).await;
+
+        match res {
+            Ok(res) => with_cleanup(res, cleanup),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Deactivates this authorization, as described in [RFC 8555 §7.5.2].
+    ///
+    /// Orders using a deactivated authorization can no longer be finalized. The cached
+    /// [API object](Self::api_auth) is replaced with the updated authorization returned by the
+    /// ACME API. Fails with [`Error::Problem`] if the ACME API refuses to deactivate it.
+    ///
+    /// [RFC 8555 §7.5.2]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.5.2
+    pub async fn deactivate(&mut self) -> eyre::Result<()> {
+        let payload = serde_json::json!({ "status": "deactivated" });
+
+        let res = self
+            .inner
+            .transport
+            .call_kid(&self.auth_url, &payload)
+            .await?;
+
+        self.api_auth = req_json(res).await?;
+
+        Ok(())
+    }
+
+    /// Returns the URL of this authorization.
+    pub(crate) fn auth_url(&self) -> &str {
+        &self.auth_url
+    }
+
+    /// Re-fetches the authorization from the ACME API.
+    ///
+    /// Updates its status and the status of its challenges, e.g., after a challenge was validated
+    /// or when resuming an order whose authorizations may already be valid.
+    pub async fn refresh(&mut self) -> eyre::Result<()> {
+        self.api_auth = fetch_authorization(&self.inner, &self.auth_url).await?;
+        Ok(())
     }
 
     /// Returns a reference to the authorization's API object.
     ///
     /// Useful for debugging.
     ///
-    /// We don't refresh the authorization when the corresponding challenge is validated, so there
-    /// will be no changes to see here.
+    /// The authorization is not refreshed automatically when the corresponding challenge is
+    /// validated; see [`refresh()`](Self::refresh).
     pub fn api_auth(&self) -> &api::Authorization {
         &self.api_auth
     }
@@ -164,9 +368,76 @@ pub struct Challenge<A> {
     inner: Arc<AccountInner>,
     api_challenge: api::Challenge,
     auth_url: String,
+    domain: String,
     _ph: std::marker::PhantomData<A>,
 }
 
+/// Type of challenge supported by this library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChallengeKind {
+    /// An `http-01` challenge.
+    Http01,
+
+    /// A `dns-01` challenge.
+    Dns01,
+
+    /// A `tls-alpn-01` challenge.
+    TlsAlpn01,
+}
+
+impl ChallengeKind {
+    /// Returns the challenge type as used by the ACME API, e.g., `"http-01"`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ChallengeKind::Http01 => "http-01",
+            ChallengeKind::Dns01 => "dns-01",
+            ChallengeKind::TlsAlpn01 => "tls-alpn-01",
+        }
+    }
+
+    fn from_type(_type: &str) -> Option<Self> {
+        match _type {
+            "http-01" => Some(ChallengeKind::Http01),
+            "dns-01" => Some(ChallengeKind::Dns01),
+            "tls-alpn-01" => Some(ChallengeKind::TlsAlpn01),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ChallengeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A challenge of any type supported by this library.
+///
+/// Returned from [`Auth::challenge_by_url()`].
+pub enum AnyChallenge {
+    /// An `http-01` challenge.
+    Http(Challenge<Http>),
+
+    /// A `dns-01` challenge.
+    Dns(Challenge<Dns>),
+
+    /// A `tls-alpn-01` challenge.
+    TlsAlpn(Challenge<TlsAlpn>),
+}
+
+impl AnyChallenge {
+    /// Returns a reference to the challenge's API object.
+    ///
+    /// Useful for debugging.
+    pub fn api_challenge(&self) -> &api::Challenge {
+        match self {
+            AnyChallenge::Http(challenge) => challenge.api_challenge(),
+            AnyChallenge::Dns(challenge) => challenge.api_challenge(),
+            AnyChallenge::TlsAlpn(challenge) => challenge.api_challenge(),
+        }
+    }
+}
+
 /// See [RFC 8555 §8.3].
 ///
 /// [RFC 8555 §8.3]: https://datatracker.ietf.org/doc/html/rfc8555#section-8.3
@@ -187,9 +458,123 @@ impl Challenge<Http> {
     /// Proof is typically placed in a text file that is served as the file named by `token`.
     pub fn http_proof(&self) -> eyre::Result<String> {
         let acme_key = self.inner.transport.acme_key();
-        let proof = key_authorization(&self.api_challenge.token, acme_key, false)?;
+        let proof = key_authorization(&self.api_challenge.token, &acme_key, false)?;
         Ok(proof)
     }
+
+    /// Adds the [proof](Self::http_proof) to `store`, to be served for this challenge's
+    /// [token](Self::http_token).
+    ///
+    /// Remove it after validation using [`ChallengeStore::remove()`].
+    pub fn register(&self, store: &ChallengeStore) -> eyre::Result<()> {
+        store.insert(self.http_token(), self.http_proof()?);
+        Ok(())
+    }
+
+    /// Writes the [proof](Self::http_proof) to `<webroot>/.well-known/acme-challenge/<token>`,
+    /// creating missing directories, and returns the path of the file.
+    ///
+    /// Use when the domain is served by a web server with `webroot` as its document root. Remove
+    /// the file after validation using [`remove_from_webroot()`](Self::remove_from_webroot).
+    pub fn write_to_webroot(&self, webroot: &Path) -> eyre::Result<PathBuf> {
+        let path = self.webroot_path(webroot)?;
+        let proof = self.http_proof()?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, proof)?;
+
+        Ok(path)
+    }
+
+    /// Removes the file written by [`write_to_webroot()`](Self::write_to_webroot).
+    ///
+    /// Succeeds if the file does not exist. The `.well-known/acme-challenge` directory is kept.
+    pub fn remove_from_webroot(&self, webroot: &Path) -> eyre::Result<()> {
+        match fs::remove_file(self.webroot_path(webroot)?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn webroot_path(&self, webroot: &Path) -> eyre::Result<PathBuf> {
+        let token = self.http_token();
+
+        // tokens are base64url-encoded, so this guards against path traversal
+        if !crate::util::is_base64url(token) {
+            return Err(eyre::eyre!("Invalid HTTP challenge token {token:?}"));
+        }
+
+        Ok(webroot
+            .join(".well-known")
+            .join("acme-challenge")
+            .join(token))
+    }
+
+    /// Checks that the proof is served at
+    /// `http://<domain-to-be-proven>/.well-known/acme-challenge/<token>`, without involving the
+    /// ACME API.
+    ///
+    /// Useful for catching misconfigured web servers before [validating](Self::validate), since a
+    /// failed validation invalidates the authorization. The request is sent using the directory's
+    /// HTTP client (see [`DirectoryBuilder::http_client()`]), which follows redirects by default.
+    /// Fails with [`Error::SelfCheckFailed`] if the request fails, the response status is not
+    /// successful, or the response body is not the [proof](Self::http_proof).
+    ///
+    /// [`DirectoryBuilder::http_client()`]: crate::DirectoryBuilder::http_client()
+    pub async fn self_check(&self) -> eyre::Result<()> {
+        self.self_check_with_base_url(&format!("http://{}", self.domain))
+            .await
+    }
+
+    /// Checks that the proof is served as in [`self_check()`](Self::self_check), but requesting
+    /// it from `base_url` (e.g., `http://10.0.0.1:8080`) instead of the domain.
+    ///
+    /// The `Host` header is still set to the domain to be proven. Useful for split-horizon DNS
+    /// setups, where the domain does not resolve to the public web server from inside the network.
+    pub async fn self_check_with_base_url(&self, base_url: &str) -> eyre::Result<()> {
+        let url = format!(
+            "{}/.well-known/acme-challenge/{}",
+            base_url.trim_end_matches('/'),
+            self.http_token(),
+        );
+
+        let self_check_failed = |reason: String| Error::SelfCheckFailed {
+            url: url.clone(),
+            reason,
+        };
+
+        let res = req_get_with_host(self.inner.transport.client(), &url, &self.domain)
+            .await
+            .map_err(|err| self_check_failed(format!("request failed: {err:#}")))?;
+
+        let status = res.status();
+
+        if !status.is_success() {
+            return Err(self_check_failed(format!("response status is {status}")).into());
+        }
+
+        let body = res
+            .text()
+            .await
+            .map_err(|err| self_check_failed(format!("reading response failed: {err}")))?;
+
+        if body.trim_end() != self.http_proof()? {
+            return Err(
+                self_check_failed(format!("response body {body:?} is not the proof")).into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the proof is served using [`self_check()`](Self::self_check) and, only if it
+    /// is, tells the ACME API to validate it as in [`validate()`](Self::validate).
+    pub async fn validate_after_self_check(&self, delay: Duration) -> eyre::Result<()> {
+        self.self_check().await?;
+        self.validate(delay).await
+    }
 }
 
 /// See [RFC 8555 §8.4].
@@ -205,9 +590,58 @@ impl Challenge<Dns> {
     /// ```
     pub fn dns_proof(&self) -> eyre::Result<String> {
         let acme_key = self.inner.transport.acme_key();
-        let proof = key_authorization(&self.api_challenge.token, acme_key, true)?;
+        let proof = key_authorization(&self.api_challenge.token, &acme_key, true)?;
         Ok(proof)
     }
+
+    /// Checks that the [proof](Self::dns_proof) is published in a `TXT` record under
+    /// `_acme-challenge.<domain-to-be-proven>`, looked up using `resolver`, without involving the
+    /// ACME API.
+    ///
+    /// Useful for waiting until the record has propagated before [validating](Self::validate),
+    /// since a failed validation invalidates the authorization. Fails with
+    /// [`Error::DnsSelfCheckFailed`], containing the records that were found, if none of them is
+    /// the proof.
+    pub async fn self_check(&self, resolver: &impl TxtResolver) -> eyre::Result<()> {
+        let record_name = dns_record_name(&self.domain);
+        let proof = self.dns_proof()?;
+
+        let found = resolver.lookup_txt(&record_name).await?;
+
+        if !found.contains(&proof) {
+            return Err(Error::DnsSelfCheckFailed { record_name, found }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the `TXT` record to publish the [proof](Self::dns_proof) in, following
+    /// `CNAME` records looked up using `resolver`, without a trailing dot.
+    ///
+    /// `_acme-challenge.<domain-to-be-proven>` is commonly delegated to a dedicated zone, e.g.,
+    /// one with a DNS provider API, using a `CNAME` record:
+    ///
+    /// ```plain
+    /// _acme-challenge.example.org.  CNAME  example.org.acme.example.net.
+    /// ```
+    ///
+    /// The ACME API follows such records, so the proof must be published under their target,
+    /// `example.org.acme.example.net` in this example. Returns
+    /// `_acme-challenge.<domain-to-be-proven>` if it is not delegated. Fails with
+    /// [`Error::CnameChainTooLong`] if the records form a loop.
+    pub async fn delegated_record_name(&self, resolver: &impl TxtResolver) -> eyre::Result<String> {
+        let record_name = dns_record_name(&self.domain);
+        let mut name = record_name.clone();
+
+        for _ in 0..MAX_CNAME_CHAIN_LENGTH {
+            match resolver.lookup_cname(&name).await? {
+                Some(target) => name = target.trim_end_matches('.').to_owned(),
+                None => return Ok(name),
+            }
+        }
+
+        Err(Error::CnameChainTooLong { record_name }.into())
+    }
 }
 
 /// See [RFC 8737 §3].
@@ -219,18 +653,37 @@ impl Challenge<TlsAlpn> {
     /// Proof is to be placed in the certificate used for validation.
     pub fn tls_alpn_proof(&self) -> eyre::Result<[u8; 32]> {
         let acme_key = self.inner.transport.acme_key();
-        let proof = key_authorization(&self.api_challenge.token, acme_key, false)?;
+        let proof = key_authorization(&self.api_challenge.token, &acme_key, false)?;
 
         Ok(Sha256::digest(proof).into())
     }
+
+    /// Returns a self-signed validation certificate for `domain` and its private key, both
+    /// PEM-encoded.
+    ///
+    /// The certificate contains `domain` as its only Subject Alternative Name and the critical
+    /// `id-pe-acmeIdentifier` extension with the [proof](Self::tls_alpn_proof), as required by
+    /// [RFC 8737 §3]. Serve it for TLS connections to `domain` that negotiate the `acme-tls/1`
+    /// ALPN protocol.
+    ///
+    /// [RFC 8737 §3]: https://datatracker.ietf.org/doc/html/rfc8737#section-3
+    pub fn tls_alpn_certificate(&self, domain: &str) -> eyre::Result<(String, Zeroizing<String>)> {
+        create_tls_alpn_certificate(domain, self.tls_alpn_proof()?)
+    }
 }
 
 impl<A> Challenge<A> {
-    fn new(inner: &Arc<AccountInner>, api_challenge: api::Challenge, auth_url: &str) -> Self {
+    fn new(
+        inner: &Arc<AccountInner>,
+        api_challenge: api::Challenge,
+        auth_url: &str,
+        domain: &str,
+    ) -> Self {
         Challenge {
             inner: inner.clone(),
             api_challenge,
             auth_url: auth_url.to_owned(),
+            domain: domain.to_owned(),
             _ph: std::marker::PhantomData,
         }
     }
@@ -246,33 +699,123 @@ impl<A> Challenge<A> {
     ///
     /// The challenge proof must be put in place before this call. Either by: placing it in a DNS
     /// record, updating a web server, or passing it to TLS connection for ALPN exchange.
+    ///
+    /// The authorization is polled every `delay` until it leaves the "pending" state, giving up
+    /// after 1 hour; see [`validate_with_timeout()`](Self::validate_with_timeout).
+    ///
+    /// It is safe to call this again for a challenge that was already triggered, e.g., by a
+    /// previous process that crashed before validation completed. If the challenge is already
+    /// "processing" or "valid", it is not triggered again and only polling resumes.
     pub async fn validate(&self, delay: Duration) -> eyre::Result<()> {
-        let res = self
-            .inner
-            .transport
-            .call_kid(&self.api_challenge.url, &api::EmptyObject)
+        self.validate_with_progress(delay, |_| {}).await
+    }
+
+    /// Tells the ACME API to attempt to validate the proof of this challenge, as in
+    /// [`validate()`](Self::validate), calling `on_poll` with the latest authorization after each
+    /// poll.
+    ///
+    /// Useful for reporting progress, e.g., printing the current status in CLIs or emitting
+    /// metrics per poll.
+    pub async fn validate_with_progress(
+        &self,
+        delay: Duration,
+        mut on_poll: impl FnMut(&api::Authorization) + Send,
+    ) -> eyre::Result<()> {
+        let deadline = tokio::time::Instant::now() + DEFAULT_VALIDATE_TIMEOUT;
+        self.validate_until(&PollConfig::new(delay), Some(deadline), &mut on_poll)
+            .await?;
+        Ok(())
+    }
+
+    /// Tells the ACME API to attempt to validate the proof of this challenge, as in
+    /// [`validate()`](Self::validate), and returns the valid authorization.
+    ///
+    /// Useful for inspecting the authorization's expiry and the time its challenge was validated,
+    /// e.g., for logging or caching decisions.
+    pub async fn validate_returning(&self, delay: Duration) -> eyre::Result<api::Authorization> {
+        let deadline = tokio::time::Instant::now() + DEFAULT_VALIDATE_TIMEOUT;
+        self.validate_until(&PollConfig::new(delay), Some(deadline), &mut |_| {})
+            .await
+    }
+
+    /// Tells the ACME API to attempt to validate the proof of this challenge, polling every
+    /// `poll_delay` for at most `total`.
+    ///
+    /// Returns [`Error::PollTimeout`] if the authorization is still "pending" when `total` has
+    /// elapsed; the last poll is made at that time.
+    ///
+    /// See [`validate()`](Self::validate).
+    pub async fn validate_with_timeout(
+        &self,
+        poll_delay: Duration,
+        total: Duration,
+    ) -> eyre::Result<()> {
+        let deadline = tokio::time::Instant::now() + total;
+        self.validate_until(&PollConfig::new(poll_delay), Some(deadline), &mut |_| {})
             .await?;
+        Ok(())
+    }
+
+    /// Tells the ACME API to attempt to validate the proof of this challenge, polling using the
+    /// directory's default [`PollConfig`].
+    ///
+    /// See [`validate()`](Self::validate) and [`DirectoryBuilder::poll_config()`].
+    ///
+    /// [`DirectoryBuilder::poll_config()`]: crate::DirectoryBuilder::poll_config()
+    pub async fn validate_default(&self) -> eyre::Result<()> {
+        self.validate_with(&self.inner.poll_config).await
+    }
 
-        let _api_challenge = res.json::<api::Challenge>().await?;
+    /// Tells the ACME API to attempt to validate the proof of this challenge, polling according
+    /// to `poll_config`.
+    ///
+    /// See [`validate()`](Self::validate).
+    pub async fn validate_with(&self, poll_config: &PollConfig) -> eyre::Result<()> {
+        self.validate_until(poll_config, None, &mut |_| {}).await?;
+        Ok(())
+    }
+
+    async fn validate_until(
+        &self,
+        poll_config: &PollConfig,
+        deadline: Option<tokio::time::Instant>,
+        on_poll: &mut (dyn FnMut(&api::Authorization) + Send),
+    ) -> eyre::Result<api::Authorization> {
+        // re-triggering a challenge can fail so check its current state first
+        let auth = fetch_authorization(&self.inner, &self.auth_url).await?;
+        let status = auth
+            .challenges
+            .iter()
+            .find(|c| c.url == self.api_challenge.url)
+            .map(|c| c.status);
 
-        let auth = poll_authorization_result(&self.inner, &self.auth_url, delay).await?;
+        if let Some(api::ChallengeStatus::Processing | api::ChallengeStatus::Valid) = status {
+            log::debug!("Challenge already triggered; resuming polling");
+        } else {
+            let res = self
+                .inner
+                .transport
+                .call_kid(&self.api_challenge.url, &api::EmptyObject)
+                .await?;
+
+            let _api_challenge = req_json::<api::Challenge>(res).await?;
+        }
+
+        let auth =
+            poll_authorization_result(&self.inner, &self.auth_url, poll_config, deadline, on_poll)
+                .await?;
 
         if !matches!(auth.status, api::AuthorizationStatus::Valid) {
-            let error = auth
-                .challenges
-                .iter()
-                .filter_map(|c| c.error.as_ref())
-                .next();
-
-            let reason = match error {
-                Some(error) => format!("{error} (subproblems: {:?})", error.subproblems),
-                None => "Validation failed and no error found".to_owned(),
-            };
-
-            return Err(eyre::eyre!("Validation failed: {reason}"));
+            let problem = auth.challenges.into_iter().find_map(|c| c.error);
+
+            return Err(Error::ValidationFailed {
+                url: self.api_challenge.url.clone(),
+                problem,
+            }
+            .into());
         }
 
-        Ok(())
+        Ok(auth)
     }
 
     /// Returns a reference to the challenge's API object.
@@ -283,13 +826,24 @@ impl<A> Challenge<A> {
     }
 }
 
-fn key_authorization(token: &str, key: &AcmeKey, extra_sha256: bool) -> eyre::Result<String> {
-    let jwk = Jwk::try_from(key)?;
-    let jwk_thumb = JwkThumb::from(&jwk);
-    let jwk_json = serde_json::to_string(&jwk_thumb)?;
+/// Returns the name of the `TXT` record for the dns challenge of `domain`, without a trailing dot.
+fn dns_record_name(domain: &str) -> String {
+    format!("_acme-challenge.{domain}")
+}
 
-    let digest = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(jwk_json));
-    let key_auth = format!("{token}.{digest}");
+/// Computes the key authorization for `token`, as defined in [RFC 8555 §8.1].
+///
+/// With `extra_sha256`, returns the base64url-encoded SHA-256 digest of it instead, as used by
+/// `dns-01` challenges.
+///
+/// [RFC 8555 §8.1]: https://datatracker.ietf.org/doc/html/rfc8555#section-8.1
+pub(crate) fn key_authorization(
+    token: &str,
+    key: &AcmeKey,
+    extra_sha256: bool,
+) -> eyre::Result<String> {
+    let thumbprint = jwk_thumbprint(key)?;
+    let key_auth = format!("{token}.{thumbprint}");
 
     let res = if extra_sha256 {
         BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(key_auth))
@@ -300,24 +854,73 @@ fn key_authorization(token: &str, key: &AcmeKey, extra_sha256: bool) -> eyre::Re
     Ok(res)
 }
 
+/// Fetches the current state of an authorization (POST-as-GET).
+async fn fetch_authorization(
+    acc: &AccountInner,
+    auth_url: &str,
+) -> eyre::Result<api::Authorization> {
+    Ok(fetch_authorization_with_retry_after(acc, auth_url).await?.0)
+}
+
+/// Fetches the current state of an authorization (POST-as-GET), along with the delay requested by
+/// the response's `Retry-After` header, if any.
+async fn fetch_authorization_with_retry_after(
+    acc: &AccountInner,
+    auth_url: &str,
+) -> eyre::Result<(api::Authorization, Option<Duration>)> {
+    let res = acc.transport.call_kid(auth_url, &api::EmptyString).await?;
+    let retry_after = req_retry_after(&res);
+    let auth = req_json::<api::Authorization>(res).await?;
+    Ok((auth, retry_after))
+}
+
 /// Polls the authorization status until it transitions out of the "pending" state.
+///
+/// Waits for the delay requested by the ACME API's `Retry-After` header between attempts, up to a
+/// limit, falling back to the poll interval when absent. The last attempt is made at `deadline`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(url = %auth_url))
+)]
 async fn poll_authorization_result(
     acc: &AccountInner,
     auth_url: &str,
-    delay: Duration,
+    poll_config: &PollConfig,
+    deadline: Option<tokio::time::Instant>,
+    on_poll: &mut (dyn FnMut(&api::Authorization) + Send),
 ) -> eyre::Result<api::Authorization> {
+    let mut attempts = 0;
+
     let auth = loop {
-        let auth = acc
-            .transport
-            .call_kid(auth_url, &api::EmptyString)
-            .await?
-            .json::<api::Authorization>()
-            .await?;
+        let (auth, retry_after) = fetch_authorization_with_retry_after(acc, auth_url).await?;
+        attempts += 1;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(attempt = attempts, status = ?auth.status, "Polled authorization");
+
+        on_poll(&auth);
 
         if !matches!(auth.status, api::AuthorizationStatus::Pending) {
             break auth;
         }
 
+        if poll_config.is_exhausted(attempts) {
+            return Err(Error::PollAttemptsExhausted {
+                url: auth_url.to_owned(),
+                attempts,
+            }
+            .into());
+        }
+
+        let Some(delay) = poll_config.next_delay(retry_after, deadline) else {
+            return Err(Error::PollTimeout {
+                url: auth_url.to_owned(),
+                attempts,
+                status: auth.status,
+            }
+            .into());
+        };
+
         tokio::time::sleep(delay).await;
     };
 
@@ -326,7 +929,49 @@ async fn poll_authorization_result(
 
 #[cfg(test)]
 mod tests {
-    use crate::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        order::{DnsSolver, SolverFuture},
+        *,
+    };
+
+    /// Solver that fails to do anything but records that cleanup was attempted.
+    #[derive(Default)]
+    struct FailingSolver {
+        removed: AtomicBool,
+    }
+
+    impl DnsSolver for FailingSolver {
+        fn set_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+            Box::pin(async { Err(eyre::eyre!("set failed")) })
+        }
+
+        fn remove_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+            self.removed.store(true, Ordering::SeqCst);
+            Box::pin(async { Err(eyre::eyre!("remove failed")) })
+        }
+    }
+
+    /// Solver that panics when setting records but records that cleanup was attempted.
+    #[derive(Default)]
+    struct PanickingSolver {
+        removed: AtomicBool,
+    }
+
+    impl DnsSolver for PanickingSolver {
+        fn set_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+            panic!("set panicked");
+        }
+
+        fn remove_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+            self.removed.store(true, Ordering::SeqCst);
+            Box::pin(async { Ok(()) })
+        }
+    }
 
     #[tokio::test]
     async fn test_get_challenges() {
@@ -348,4 +993,568 @@ mod tests {
         let dns = auth.dns_challenge().unwrap();
         assert!(dns.need_validate());
     }
+
+    #[tokio::test]
+    async fn test_dns_record() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let auth = &authz[0];
+
+        assert_eq!(auth.dns_record(), "_acme-challenge.acmetest.algesten.se");
+        assert_eq!(
+            auth.dns_record_fqdn(),
+            "_acme-challenge.acmetest.algesten.se."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_challenge_by_url() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let auth = &authz[0];
+
+        let dns = auth.dns_challenge().unwrap();
+        let dns_url = &dns.api_challenge().url;
+        assert!(matches!(
+            auth.challenge_by_url(dns_url),
+            Some(order::AnyChallenge::Dns(challenge)) if &challenge.api_challenge().url == dns_url,
+        ));
+        assert!(auth
+            .challenge_by_url("https://example.com/unknown")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_require_challenge() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        assert!(matches!(
+            authz[0].require_challenge(order::ChallengeKind::Http01),
+            Ok(order::AnyChallenge::Http(_)),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_require_challenge_unavailable() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let mut authz = ord.authorizations().await.unwrap();
+
+        // pretend the ACME API provider does not offer tls-alpn-01
+        authz[0]
+            .api_auth
+            .challenges
+            .retain(|c| c._type != "tls-alpn-01");
+
+        let err = authz[0]
+            .require_challenge(order::ChallengeKind::TlsAlpn01)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::ChallengeTypeUnavailable { kind: order::ChallengeKind::TlsAlpn01, available })
+                if available == &["http-01", "dns-01"],
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_solve_dns_cleans_up_on_failure() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let solver = FailingSolver::default();
+        let err = authz[0]
+            .solve_dns(&solver, std::time::Duration::from_millis(1))
+            .await
+            .unwrap_err();
+
+        assert!(solver.removed.load(Ordering::SeqCst));
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Cleanup { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_solve_dns_cleans_up_on_panic() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let solver = Arc::new(PanickingSolver::default());
+        let task = tokio::spawn({
+            let solver = Arc::clone(&solver);
+            async move {
+                authz[0]
+                    .solve_dns(&*solver, std::time::Duration::from_millis(1))
+                    .await
+            }
+        });
+
+        assert!(task.await.unwrap_err().is_panic());
+        assert!(solver.removed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        // the authorization becomes valid once the challenge is validated
+        let validated = Arc::new(AtomicBool::new(false));
+        let dir = Directory::builder(url)
+            .rewrite_url({
+                let validated = Arc::clone(&validated);
+                move |url| {
+                    if validated.load(Ordering::SeqCst) {
+                        url.replace("/acme/authz/", "/acme/authz-valid/")
+                    } else {
+                        url.to_owned()
+                    }
+                }
+            })
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let mut authz = ord.authorizations().await.unwrap();
+        assert_eq!(
+            authz[0].api_auth().status,
+            api::AuthorizationStatus::Pending
+        );
+        assert!(authz[0].need_challenge());
+
+        validated.store(true, Ordering::SeqCst);
+
+        authz[0].refresh().await.unwrap();
+        assert_eq!(authz[0].domain_name(), "acmetest.algesten.se");
+        assert_eq!(authz[0].api_auth().status, api::AuthorizationStatus::Valid);
+        assert!(!authz[0].need_challenge());
+    }
+
+    #[tokio::test]
+    async fn test_validate_resumes_persisted_order() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        // the challenge was triggered before a crash; triggering it again fails
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| {
+                url.replace("/acme/authz/", "/acme/authz-valid/")
+                    .replace("/acme/challenge/", "/acme/challenge-triggered/")
+            })
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+
+        // only the order URL was persisted
+        let order_url = server
+            .dir_url
+            .replace("/directory", "/acme/order-unexpired/YTqpYUthlVfwBncUufE8");
+        let ord = acc.order_from_url(&order_url).await.unwrap();
+
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].http_challenge().unwrap();
+        challenge
+            .validate(std::time::Duration::from_millis(1))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_failed_with_subproblems() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-invalid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let err = authz[0]
+            .http_challenge()
+            .unwrap()
+            .validate_with(&PollConfig::single_attempt())
+            .await
+            .unwrap_err();
+
+        let Some(Error::ValidationFailed {
+            problem: Some(problem),
+            ..
+        }) = err.downcast_ref::<Error>()
+        else {
+            panic!("expected validation failure with problem: {err}");
+        };
+
+        let subproblems = problem.subproblems.as_deref().unwrap();
+        assert_eq!(subproblems.len(), 2);
+        assert_eq!(subproblems[0].problem_type(), Some(api::ProblemType::Caa));
+        assert_eq!(
+            subproblems[1].identifier.as_ref().unwrap().value,
+            "www.acmetest.algesten.se",
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "Validation failed: urn:ietf:params:acme:error:compound: Errors for multiple identifiers; \
+             acmetest.algesten.se: urn:ietf:params:acme:error:caa: CAA record forbids issuance; \
+             www.acmetest.algesten.se: urn:ietf:params:acme:error:dns: No valid IP addresses found",
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_validate_with_timeout() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        // without timers of its own, the client does not let the paused clock skip ahead
+        let http_client = reqwest::Client::builder()
+            .pool_idle_timeout(None)
+            .build()
+            .unwrap();
+        let dir = Directory::builder(url)
+            .http_client(http_client)
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let err = authz[0]
+            .http_challenge()
+            .unwrap()
+            .validate_with_timeout(
+                std::time::Duration::from_secs(20),
+                std::time::Duration::from_secs(50),
+            )
+            .await
+            .unwrap_err();
+
+        // polled after 0, 20, 40, and 50 seconds
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::PollTimeout {
+                attempts: 4,
+                status: api::AuthorizationStatus::Pending,
+                ..
+            }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_single_attempt() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let err = authz[0]
+            .http_challenge()
+            .unwrap()
+            .validate_with(&PollConfig::single_attempt())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::PollAttemptsExhausted { attempts: 1, .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_returning() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-valid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let auth = authz[0]
+            .http_challenge()
+            .unwrap()
+            .validate_returning(std::time::Duration::from_millis(1))
+            .await
+            .unwrap();
+
+        assert_eq!(auth.status, api::AuthorizationStatus::Valid);
+        assert_eq!(auth.expires.as_deref(), Some("2019-01-09T08:26:43Z"));
+        assert_eq!(
+            auth.challenges[0].validated.as_deref(),
+            Some("2019-01-02T08:26:43Z"),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_with_progress() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-valid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+
+        let mut statuses = Vec::new();
+        authz[0]
+            .http_challenge()
+            .unwrap()
+            .validate_with_progress(std::time::Duration::from_millis(1), |auth| {
+                statuses.push(auth.status)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(statuses, [api::AuthorizationStatus::Valid]);
+    }
+
+    #[tokio::test]
+    async fn test_http_webroot() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].http_challenge().unwrap();
+
+        let webroot = std::env::temp_dir().join(format!("acme-webroot-{}", std::process::id()));
+
+        let path = challenge.write_to_webroot(&webroot).unwrap();
+        assert_eq!(
+            path,
+            webroot
+                .join(".well-known/acme-challenge")
+                .join(challenge.http_token()),
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            challenge.http_proof().unwrap(),
+        );
+
+        // existing directories are reused
+        assert_eq!(challenge.write_to_webroot(&webroot).unwrap(), path);
+
+        challenge.remove_from_webroot(&webroot).unwrap();
+        assert!(!path.exists());
+        challenge.remove_from_webroot(&webroot).unwrap();
+
+        std::fs::remove_dir_all(&webroot).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_register() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].http_challenge().unwrap();
+
+        let store = order::ChallengeStore::new();
+        challenge.register(&store).unwrap();
+
+        assert_eq!(
+            store.respond(challenge.http_token()),
+            Some(challenge.http_proof().unwrap()),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_self_check() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].http_challenge().unwrap();
+
+        let base_url =
+            crate::test::with_static_http_server("200 OK", challenge.http_proof().unwrap());
+        challenge.self_check_with_base_url(&base_url).await.unwrap();
+
+        let base_url = crate::test::with_static_http_server("200 OK", "wrong".to_owned());
+        let err = challenge
+            .self_check_with_base_url(&base_url)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::SelfCheckFailed { reason, .. }) if reason.contains("not the proof"),
+        ));
+
+        let base_url = crate::test::with_static_http_server("404 Not Found", String::new());
+        let err = challenge
+            .self_check_with_base_url(&base_url)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::SelfCheckFailed { reason, .. }) if reason.contains("404"),
+        ));
+    }
+
+    struct StaticResolver(Vec<String>);
+
+    impl order::TxtResolver for StaticResolver {
+        fn lookup_txt<'a>(&'a self, record_name: &'a str) -> order::TxtLookupFuture<'a> {
+            assert_eq!(record_name, "_acme-challenge.acmetest.algesten.se");
+            Box::pin(async { Ok(self.0.clone()) })
+        }
+
+        fn lookup_cname<'a>(&'a self, _name: &'a str) -> order::CnameLookupFuture<'a> {
+            Box::pin(async { Ok(None) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_self_check() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].dns_challenge().unwrap();
+
+        let resolver = StaticResolver(vec!["stale".to_owned(), challenge.dns_proof().unwrap()]);
+        challenge.self_check(&resolver).await.unwrap();
+
+        let resolver = StaticResolver(vec!["stale".to_owned()]);
+        let err = challenge.self_check(&resolver).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::DnsSelfCheckFailed { found, .. }) if found == &["stale"],
+        ));
+    }
+
+    struct CnameResolver(Vec<(&'static str, &'static str)>);
+
+    impl order::TxtResolver for CnameResolver {
+        fn lookup_txt<'a>(&'a self, _record_name: &'a str) -> order::TxtLookupFuture<'a> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+
+        fn lookup_cname<'a>(&'a self, name: &'a str) -> order::CnameLookupFuture<'a> {
+            Box::pin(async move {
+                Ok(self
+                    .0
+                    .iter()
+                    .find(|(from, _)| *from == name)
+                    .map(|(_, to)| (*to).to_owned()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_delegated_record_name() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let authz = ord.authorizations().await.unwrap();
+        let challenge = authz[0].dns_challenge().unwrap();
+
+        let resolver = CnameResolver(vec![]);
+        let name = challenge.delegated_record_name(&resolver).await.unwrap();
+        assert_eq!(name, "_acme-challenge.acmetest.algesten.se");
+
+        let resolver = CnameResolver(vec![
+            (
+                "_acme-challenge.acmetest.algesten.se",
+                "acmetest.algesten.se.acme.example.net.",
+            ),
+            ("acmetest.algesten.se.acme.example.net", "txt.example.net."),
+        ]);
+        let name = challenge.delegated_record_name(&resolver).await.unwrap();
+        assert_eq!(name, "txt.example.net");
+
+        let resolver = CnameResolver(vec![
+            ("_acme-challenge.acmetest.algesten.se", "a.example.net"),
+            ("a.example.net", "_acme-challenge.acmetest.algesten.se"),
+        ]);
+        let err = challenge
+            .delegated_record_name(&resolver)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::CnameChainTooLong { record_name })
+                if record_name == "_acme-challenge.acmetest.algesten.se",
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deactivate() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let mut authz = ord.authorizations().await.unwrap();
+
+        authz[0].deactivate().await.unwrap();
+        assert_eq!(authz[0].domain_name(), "acmetest.algesten.se");
+        assert_eq!(
+            authz[0].api_auth().status,
+            api::AuthorizationStatus::Deactivated,
+        );
+    }
 }
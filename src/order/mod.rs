@@ -11,27 +11,48 @@
 //!
 //! \* Possibly multiple auths.
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use base64::prelude::*;
 use der::Encode as _;
 use pkcs8::EncodePrivateKey as _;
+use time::OffsetDateTime;
+use tokio::sync::OwnedSemaphorePermit;
+use zeroize::Zeroizing;
 
 use crate::{
     acc::AccountInner,
     api,
-    cert::{create_csr, Certificate},
+    cert::{check_csr_identifiers, create_csr, Certificate, CsrParams},
+    req::{req_bytes, req_json, req_links, req_retry_after, req_text},
+    Error, PollConfig,
 };
 
 mod auth;
+mod solver;
+mod store;
 
-pub use self::auth::{Auth, Challenge};
+pub(crate) use self::auth::key_authorization;
+pub use self::{
+    auth::{AnyChallenge, Auth, Challenge, ChallengeKind},
+    solver::{
+        CnameLookupFuture, DnsSolver, HttpSolver, ManualSolver, NoopSolver, SolverFuture,
+        TxtLookupFuture, TxtResolver,
+    },
+    store::ChallengeStore,
+};
+
+/// Time limit for an order to leave the "processing" status after submitting a CSR.
+const DEFAULT_FINALIZE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
 /// The order wrapped with an outer facade.
 pub(crate) struct Order {
     acc: Arc<AccountInner>,
     pub(crate) api_order: api::Order,
     url: String,
+
+    /// Counts this order against the account's in-flight order limit, if there is one.
+    pub(crate) permit: Option<Arc<OwnedSemaphorePermit>>,
 }
 
 impl Order {
@@ -40,8 +61,39 @@ impl Order {
             acc: Arc::clone(acc),
             api_order,
             url,
+            permit: None,
         }
     }
+
+    /// Checks that the order can still progress, i.e., it is not invalid and, if it still needs
+    /// authorizations or finalizing, has not expired.
+    pub(crate) fn check_resumable(&self) -> eyre::Result<()> {
+        match self.api_order.status {
+            Some(api::OrderStatus::Invalid) => {
+                return Err(Error::OrderInvalid {
+                    url: self.url.clone(),
+                    problem: self.api_order.error.clone(),
+                }
+                .into());
+            }
+
+            Some(api::OrderStatus::Pending | api::OrderStatus::Ready) => {}
+
+            _ => return Ok(()),
+        }
+
+        if let Some(expires_at) = self.api_order.expires_at()? {
+            if expires_at <= OffsetDateTime::now_utc() {
+                return Err(Error::OrderExpired {
+                    url: self.url.clone(),
+                    expires: self.api_order.expires.clone().unwrap_or_default(),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Helper to refresh an order status (POST-as-GET).
@@ -50,28 +102,44 @@ pub(crate) async fn refresh_order(
     url: String,
     want_status: &'static str,
 ) -> eyre::Result<Order> {
+    Ok(refresh_order_with_retry_after(acc, url, want_status)
+        .await?
+        .0)
+}
+
+/// Refreshes the order as in [`refresh_order()`], along with the delay requested by the response's
+/// `Retry-After` header, if any.
+async fn refresh_order_with_retry_after(
+    acc: &Arc<AccountInner>,
+    url: String,
+    want_status: &'static str,
+) -> eyre::Result<(Order, Option<Duration>)> {
     let res = acc.transport.call_kid(&url, &api::EmptyString).await?;
+    let retry_after = req_retry_after(&res);
 
     // our test rig requires the order to be in `want_status`.
     // api_order_of is different for test compilation
     let api_order = api_order_of(res, want_status).await?;
 
-    Ok(Order {
+    let order = Order {
         acc: Arc::clone(acc),
         api_order,
         url,
-    })
+        permit: None,
+    };
+
+    Ok((order, retry_after))
 }
 
 #[cfg(not(test))]
 async fn api_order_of(res: reqwest::Response, _want_status: &str) -> eyre::Result<api::Order> {
-    Ok(res.json().await?)
+    req_json(res).await
 }
 
 #[cfg(test)]
 // our test rig requires the order to be in `want_status`
 async fn api_order_of(res: reqwest::Response, want_status: &str) -> eyre::Result<api::Order> {
-    let body = res.text().await?;
+    let body = req_text(res).await?;
 
     #[allow(clippy::trivial_regex)]
     let re = regex::Regex::new("<STATUS>").unwrap();
@@ -124,20 +192,44 @@ impl NewOrder {
     pub fn confirm_validations(&self) -> Option<CsrOrder> {
         if self.is_validated() {
             Some(CsrOrder {
-                order: Order::new(
-                    &self.order.acc,
-                    self.order.api_order.clone(),
-                    self.order.url.clone(),
-                ),
+                order: Order {
+                    acc: Arc::clone(&self.order.acc),
+                    api_order: self.order.api_order.clone(),
+                    url: self.order.url.clone(),
+                    permit: self.order.permit.clone(),
+                },
             })
         } else {
             None
         }
     }
 
+    /// Returns the status of the order, as of the last [`refresh`].
+    ///
+    /// Useful for resuming an order from a previous attempt, e.g., skipping challenges for an
+    /// order that is already `ready`. See [RFC 8555 §7.1.6] for the state machine.
+    ///
+    /// This doesn't do any calls against the API.
+    ///
+    /// [`refresh`]: Self::refresh
+    /// [RFC 8555 §7.1.6]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.1.6
+    pub fn status(&self) -> Option<api::OrderStatus> {
+        self.order.api_order.status
+    }
+
+    /// Returns the URL of the issued certificate, as of the last [`refresh`].
+    ///
+    /// Only present once the order is `valid`.
+    ///
+    /// [`refresh`]: Self::refresh
+    pub fn certificate_url(&self) -> Option<&str> {
+        self.order.api_order.certificate.as_deref()
+    }
+
     /// Refresh the order state against the ACME API.
     ///
-    /// The specification calls this a "POST-as-GET" against the order URL.
+    /// The specification calls this a "POST-as-GET" against the order URL. Use
+    /// [`status()`](Self::status) to inspect the refreshed state.
     pub async fn refresh(&mut self) -> eyre::Result<()> {
         let order = refresh_order(&self.order.acc, self.order.url.clone(), "ready").await?;
         self.order.api_order.overwrite(order.api_order)?;
@@ -160,13 +252,134 @@ impl NewOrder {
                     .transport
                     .call_kid(auth_url, &api::EmptyString)
                     .await?;
-                let api_auth = res.json::<api::Authorization>().await?;
+                let api_auth = req_json::<api::Authorization>(res).await?;
                 result.push(Auth::new(&self.order.acc, api_auth, auth_url));
             }
         }
         Ok(result)
     }
 
+    /// Counts the authorizations of this order that were reused from previous orders versus those
+    /// that still [need a challenge](Auth::need_challenge).
+    ///
+    /// Authorizations that are invalid, expired, deactivated, or revoked can neither be reused nor
+    /// satisfied by solving a challenge; they are counted as [`failed`](AuthorizationCounts::failed).
+    ///
+    /// Useful for metrics on how often the ACME API provider's authorization reuse saves validation
+    /// work, e.g., when renewing.
+    pub async fn authorization_counts(&self) -> eyre::Result<AuthorizationCounts> {
+        let authorizations = self.authorizations().await?;
+
+        let mut counts = AuthorizationCounts {
+            reused: 0,
+            required: 0,
+            failed: 0,
+        };
+
+        for auth in &authorizations {
+            match auth.api_auth().status {
+                api::AuthorizationStatus::Valid => counts.reused += 1,
+                api::AuthorizationStatus::Pending => counts.required += 1,
+                api::AuthorizationStatus::Invalid
+                | api::AuthorizationStatus::Deactivated
+                | api::AuthorizationStatus::Expired
+                | api::AuthorizationStatus::Revoked => counts.failed += 1,
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Finalizes the order without solving any challenges, if all of its authorizations are
+    /// already valid.
+    ///
+    /// Useful for renewals, when the ACME API provider still remembers recent proofs of ownership
+    /// of all domains in the order. Fails with [`Error::ChallengesRequired`] if any authorization
+    /// [needs a challenge](Auth::need_challenge). Otherwise, waits until the order is ready,
+    /// polling according to `poll_config`, and then finalizes it as in [`CsrOrder::finalize()`],
+    /// polling the same way while it is processing. Fails with [`Error::PollAttemptsExhausted`] if
+    /// the order is still pending or processing after the configured number of attempts.
+    pub async fn try_finalize_if_ready(
+        mut self,
+        private_key: p256::ecdsa::SigningKey,
+        poll_config: &PollConfig,
+    ) -> eyre::Result<CertOrder> {
+        let domains = self
+            .authorizations()
+            .await?
+            .iter()
+            .filter(|auth| auth.need_challenge())
+            .map(|auth| auth.domain_name().to_owned())
+            .collect::<Vec<_>>();
+
+        if !domains.is_empty() {
+            return Err(Error::ChallengesRequired { domains }.into());
+        }
+
+        let mut attempts = 0;
+
+        // the order becomes "ready" shortly after all of its authorizations are valid
+        loop {
+            if let Some(csr_order) = self.confirm_validations() {
+                return csr_order
+                    .finalize_polling(private_key, &CsrParams::default(), poll_config)
+                    .await;
+            }
+
+            if !matches!(self.order.api_order.status, Some(api::OrderStatus::Pending)) {
+                return Err(eyre::eyre!(
+                    "Order is in status: {:?}",
+                    self.order.api_order.status
+                ));
+            }
+
+            attempts += 1;
+
+            if poll_config.is_exhausted(attempts) {
+                return Err(Error::PollAttemptsExhausted {
+                    url: self.order.url.clone(),
+                    attempts,
+                }
+                .into());
+            }
+
+            tokio::time::sleep(poll_config.interval()).await;
+            self.refresh().await?;
+        }
+    }
+
+    /// Returns a map of `http-01` challenge tokens to proofs for all authorizations of this order
+    /// that still need a challenge.
+    ///
+    /// Useful for a single responder serving `/.well-known/acme-challenge/<token>` for many
+    /// concurrent orders; extend its map with the result for each new order, e.g.:
+    ///
+    /// ```no_run
+    /// # async fn serve(orders: Vec<acme::order::NewOrder>) -> eyre::Result<()> {
+    /// let mut proofs = std::collections::HashMap::new();
+    ///
+    /// for order in &orders {
+    ///     proofs.extend(order.http_proofs().await?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn http_proofs(&self) -> eyre::Result<HashMap<String, String>> {
+        let mut proofs = HashMap::new();
+
+        for auth in self.authorizations().await? {
+            if !auth.need_challenge() {
+                continue;
+            }
+
+            if let Some(challenge) = auth.http_challenge() {
+                proofs.insert(challenge.http_token().to_owned(), challenge.http_proof()?);
+            }
+        }
+
+        Ok(proofs)
+    }
+
     /// Returns a reference to the order's API object.
     ///
     /// Useful for debugging.
@@ -175,6 +388,23 @@ impl NewOrder {
     }
 }
 
+/// Numbers of reused, required, and failed authorizations of an order.
+///
+/// Returned from [`NewOrder::authorization_counts()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorizationCounts {
+    /// Number of authorizations that are already valid.
+    pub reused: usize,
+
+    /// Number of authorizations that need a challenge to be solved.
+    pub required: usize,
+
+    /// Number of authorizations that are invalid, expired, deactivated, or revoked.
+    ///
+    /// An order with failed authorizations can not be completed; a new order is needed.
+    pub failed: usize,
+}
+
 /// An order that is ready for a [CSR] submission.
 ///
 /// Submitting the CSR is called "finalizing" the order.
@@ -199,18 +429,120 @@ impl CsrOrder {
     ///
     /// Once the CSR has been submitted, the order goes into a "processing" status, where we must
     /// poll until the status changes to "valid"; `interval` is the amount of time to wait between
-    /// each poll attempt.
+    /// each poll attempt. Fails with [`Error::FinalizeTimeout`] if the order is still processing
+    /// after an hour.
     pub async fn finalize(
-        mut self,
+        self,
+        private_key: p256::ecdsa::SigningKey,
+        interval: Duration,
+    ) -> eyre::Result<CertOrder> {
+        self.finalize_with_csr_params(private_key, interval, &CsrParams::default())
+            .await
+    }
+
+    /// Finalizes the order as in [`finalize()`](Self::finalize), using `params` for the subject of
+    /// the CSR.
+    ///
+    /// Useful for meeting the subject naming policies of internal PKIs. The CSR's Subject
+    /// Alternative Name extension still contains the domains in the order.
+    pub async fn finalize_with_csr_params(
+        self,
         private_key: p256::ecdsa::SigningKey,
         interval: Duration,
+        params: &CsrParams,
     ) -> eyre::Result<CertOrder> {
+        self.finalize_polling(private_key, params, &PollConfig::new(interval))
+            .await
+    }
+
+    /// Finalizes the order as in [`finalize_with_csr_params()`](Self::finalize_with_csr_params),
+    /// polling according to `poll_config`.
+    async fn finalize_polling(
+        self,
+        private_key: p256::ecdsa::SigningKey,
+        params: &CsrParams,
+        poll_config: &PollConfig,
+    ) -> eyre::Result<CertOrder> {
+        let csr_der = self.csr_der(&private_key, params)?;
+        log::debug!("Submitting CSR for: {:?}", self.order.api_order.domains());
+
+        self.submit_csr(Some(private_key), csr_der, poll_config)
+            .await
+    }
+
+    /// Returns the DER-encoded CSR that [`finalize_with_csr_params()`](Self::finalize_with_csr_params)
+    /// submits for `private_key` and `params`.
+    ///
+    /// Useful for keeping an audit record of a CSR before submitting it, so that it is not lost if
+    /// finalizing fails. The CSR is identical to the submitted one since its ECDSA signature is
+    /// deterministic ([RFC 6979]).
+    ///
+    /// [RFC 6979]: https://www.rfc-editor.org/rfc/rfc6979
+    pub fn csr_der(
+        &self,
+        private_key: &p256::ecdsa::SigningKey,
+        params: &CsrParams,
+    ) -> eyre::Result<Vec<u8>> {
         // the domains that we have authorized
         let domains = self.order.api_order.domains();
 
-        let csr = create_csr(&private_key, &domains)?;
+        let csr = create_csr(private_key, &domains, params)?;
+
+        Ok(csr.to_der()?)
+    }
+
+    /// Finalizes the order as in [`finalize_with_csr_params()`](Self::finalize_with_csr_params)
+    /// and downloads the issued certificate.
+    ///
+    /// While the order is "processing", it is polled every `interval`, or as requested by the
+    /// ACME API's `Retry-After` header. Fails with [`Error::OrderInvalid`], containing the order's
+    /// problem document, if issuance fails.
+    pub async fn finalize_and_wait(
+        self,
+        private_key: p256::ecdsa::SigningKey,
+        params: &CsrParams,
+        interval: Duration,
+    ) -> eyre::Result<Certificate> {
+        self.finalize_with_csr_params(private_key, interval, params)
+            .await?
+            .download_cert()
+            .await
+    }
+
+    /// Finalizes the order by submitting the DER-encoded PKCS #10 CSR `csr_der` as-is, and awaiting
+    /// certificate issuance as in [`finalize()`](Self::finalize).
+    ///
+    /// Useful when the certificate's private key must not leave an HSM. Before submitting, checks
+    /// that the identifiers requested by the CSR are exactly those of the order, failing with
+    /// [`Error::CsrIdentifiersMismatch`] otherwise.
+    ///
+    /// Since the private key is not known, the issued certificate can not be downloaded using
+    /// [`CertOrder::download_cert()`]; use [`CertOrder::all_certificate_chains()`] or
+    /// [`CertOrder::preferred_certificate_chain()`] instead.
+    pub async fn finalize_with_der_csr(
+        self,
+        csr_der: &[u8],
+        interval: Duration,
+    ) -> eyre::Result<CertOrder> {
+        check_csr_identifiers(csr_der, &self.order.api_order.identifiers)?;
+
+        log::debug!("Submitting external CSR");
+
+        self.submit_csr(None, csr_der.to_vec(), &PollConfig::new(interval))
+            .await
+    }
 
-        let csr_der = csr.to_der()?;
+    /// Submits `csr_der` and polls until the order is no longer processing.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(url = %self.order.url))
+    )]
+    async fn submit_csr(
+        mut self,
+        private_key: Option<p256::ecdsa::SigningKey>,
+        csr_der: Vec<u8>,
+        poll_config: &PollConfig,
+    ) -> eyre::Result<CertOrder> {
         let csr_b64 = BASE64_URL_SAFE_NO_PAD.encode(&csr_der);
         let finalize = api::Finalize::new(csr_b64);
 
@@ -222,10 +554,22 @@ impl CsrOrder {
         // from this retry_call.
         inner.transport.call_kid(finalize_url, &finalize).await?;
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Submitted CSR");
+
         // wait for the status to not be processing:
         // valid -> cert is issued
         // invalid -> the whole thing is off
-        let order = poll_order_finalization(inner, order_url, interval).await?;
+        let deadline = tokio::time::Instant::now() + DEFAULT_FINALIZE_TIMEOUT;
+        let order = poll_order_finalization(inner, order_url, poll_config, Some(deadline)).await?;
+
+        if let Some(api::OrderStatus::Invalid) = order.api_order.status {
+            return Err(Error::OrderInvalid {
+                url: order_url.clone(),
+                problem: order.api_order.error,
+            }
+            .into());
+        }
 
         if !matches!(order.api_order.status, Some(api::OrderStatus::Valid)) {
             return Err(eyre::eyre!(
@@ -238,10 +582,50 @@ impl CsrOrder {
 
         Ok(CertOrder {
             private_key,
+            csr_der,
             order: self.order,
         })
     }
 
+    /// Finalizes the order as in [`finalize()`](Self::finalize), but only if it does not expire
+    /// within `margin`.
+    ///
+    /// Avoids submitting a CSR to an order that may lapse before the certificate is issued. Fails
+    /// with [`Error::OrderExpiring`] without submitting the CSR if the order's `expires` time is less
+    /// than `margin` away. Orders without an `expires` time are always finalized.
+    pub async fn finalize_with_margin(
+        self,
+        private_key: p256::ecdsa::SigningKey,
+        interval: Duration,
+        margin: Duration,
+    ) -> eyre::Result<CertOrder> {
+        if let Some(expires_at) = self.order.api_order.expires_at()? {
+            if expires_at - OffsetDateTime::now_utc() < margin {
+                return Err(Error::OrderExpiring {
+                    url: self.order.url.clone(),
+                    expires: self.order.api_order.expires.clone().unwrap_or_default(),
+                }
+                .into());
+            }
+        }
+
+        self.finalize(private_key, interval).await
+    }
+
+    /// Finalizes an order created by [`Account::renew()`] as in [`finalize()`](Self::finalize),
+    /// reusing the private key of the `previous` certificate.
+    ///
+    /// Useful for keeping the same certificate key, e.g., when the public key is pinned.
+    ///
+    /// [`Account::renew()`]: crate::Account::renew()
+    pub async fn finalize_renewal(
+        self,
+        previous: &Certificate,
+        interval: Duration,
+    ) -> eyre::Result<CertOrder> {
+        self.finalize(previous.signing_key()?, interval).await
+    }
+
     /// Returns a reference to the order's API object.
     ///
     /// Useful for debugging.
@@ -251,31 +635,124 @@ impl CsrOrder {
 }
 
 /// Polls the order status until it transitions out of the "processing" state.
+///
+/// Waits for the delay requested by the ACME API's `Retry-After` header between attempts, up to a
+/// limit, falling back to the poll interval when absent. The last attempt is made at `deadline`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(url = %url))
+)]
 async fn poll_order_finalization(
     acc: &Arc<AccountInner>,
     url: &str,
-    interval: Duration,
+    poll_config: &PollConfig,
+    deadline: Option<tokio::time::Instant>,
 ) -> eyre::Result<Order> {
+    let mut attempts = 0;
+
     loop {
-        let order = refresh_order(acc, url.to_owned(), "valid").await?;
+        let (order, retry_after) =
+            refresh_order_with_retry_after(acc, url.to_owned(), "valid").await?;
+        attempts += 1;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(attempt = attempts, status = ?order.api_order.status, "Polled order");
 
         if !matches!(order.api_order.status, Some(api::OrderStatus::Processing)) {
             return Ok(order);
         }
 
-        tokio::time::sleep(interval).await;
+        if poll_config.is_exhausted(attempts) {
+            return Err(Error::PollAttemptsExhausted {
+                url: url.to_owned(),
+                attempts,
+            }
+            .into());
+        }
+
+        let Some(delay) = poll_config.next_delay(retry_after, deadline) else {
+            return Err(Error::FinalizeTimeout {
+                url: url.to_owned(),
+                attempts,
+            }
+            .into());
+        };
+
+        tokio::time::sleep(delay).await;
     }
 }
 
 /// Order for an issued certificate that is ready to download.
 pub struct CertOrder {
-    private_key: p256::ecdsa::SigningKey,
+    /// Not known if the order was finalized with an external CSR.
+    private_key: Option<p256::ecdsa::SigningKey>,
+    csr_der: Vec<u8>,
     order: Order,
 }
 
 impl CertOrder {
+    /// Returns the DER-encoded CSR that was submitted when finalizing the order.
+    ///
+    /// Useful for keeping an audit record of CSRs submitted to the ACME API provider.
+    pub fn csr_der(&self) -> &[u8] {
+        &self.csr_der
+    }
+
+    /// Returns the PEM-encoded CSR that was submitted when finalizing the order.
+    ///
+    /// See [`csr_der()`](Self::csr_der).
+    pub fn csr_pem(&self) -> eyre::Result<String> {
+        pem::encode_string("CERTIFICATE REQUEST", pem::LineEnding::LF, &self.csr_der)
+            .map_err(|err| eyre::eyre!("Failed to encode CSR as PEM: {err}"))
+    }
+
+    /// Returns the PEM-encoded private key of the certificate, generated by the caller when
+    /// [finalizing](CsrOrder::finalize) the order.
+    ///
+    /// Fails with [`Error::PrivateKeyUnknown`] if the order was
+    /// [finalized with an external CSR](CsrOrder::finalize_with_der_csr).
+    pub fn private_key_pem(&self) -> eyre::Result<Zeroizing<String>> {
+        let private_key = self
+            .private_key
+            .as_ref()
+            .ok_or_else(|| Error::PrivateKeyUnknown {
+                url: self.order.url.clone(),
+            })?;
+
+        Ok(private_key.to_pkcs8_pem(der::pem::LineEnding::LF)?)
+    }
+
     /// Request download of the issued certificate.
+    ///
+    /// Fails if the order was [finalized with an external CSR](CsrOrder::finalize_with_der_csr).
     pub async fn download_cert(self) -> eyre::Result<Certificate> {
+        self.download_cert_with_retry(1, Duration::ZERO).await
+    }
+
+    /// Request download of the issued certificate, retrying if it is not available yet.
+    ///
+    /// Some ACME API providers serve certificates from an eventually-consistent store and briefly
+    /// respond with `404 Not Found` or `403 Forbidden` after the order becomes valid. Such responses
+    /// are retried up to `max_attempts` attempts in total, waiting `backoff` before the first retry
+    /// and doubling the wait after each one. If the certificate is still unavailable, fails with
+    /// [`Error::CertificateUnavailable`].
+    ///
+    /// Other errors, including the order not being valid or having no certificate URL, are returned
+    /// immediately.
+    pub async fn download_cert_with_retry(
+        self,
+        max_attempts: u32,
+        mut backoff: Duration,
+    ) -> eyre::Result<Certificate> {
+        if !matches!(self.order.api_order.status, Some(api::OrderStatus::Valid)) {
+            return Err(eyre::eyre!(
+                "Order is in status: {:?}",
+                self.order.api_order.status
+            ));
+        }
+
+        let private_key_pem = self.private_key_pem()?;
+
         let url = self
             .order
             .api_order
@@ -283,16 +760,98 @@ impl CertOrder {
             .ok_or_else(|| eyre::eyre!("certificate url"))?;
 
         let inner = self.order.acc;
+        let mut attempts = 0;
+
+        let res = loop {
+            attempts += 1;
+
+            let err = match inner.transport.call_kid(&url, &api::EmptyString).await {
+                Ok(res) => break res,
+                Err(err) => err,
+            };
+
+            let not_yet_available = matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::Problem { problem, .. }) if matches!(problem.status, Some(403 | 404)),
+            );
 
-        let res = inner.transport.call_kid(&url, &api::EmptyString).await?;
+            if !not_yet_available {
+                return Err(err);
+            }
+
+            if attempts >= max_attempts {
+                return Err(Error::CertificateUnavailable { url, attempts }.into());
+            }
 
-        let private_key_pem = self.private_key.to_pkcs8_pem(der::pem::LineEnding::LF)?;
+            log::debug!("Certificate not available yet, retrying: {err}");
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        };
 
-        let certificate = res.text().await?;
+        let certificate = req_text(res).await?;
 
         Ok(Certificate::new(private_key_pem, certificate))
     }
 
+    /// Downloads the default certificate chain and all alternate chains offered by the ACME API.
+    ///
+    /// The first element is the default chain, tagged with `None`. Alternate chains, linked from
+    /// the default chain's response with `Link: <url>;rel="alternate"` headers as described in
+    /// [RFC 8555 §7.4.2], follow in the order they were linked, each tagged with its URL. Chains are
+    /// returned exactly as downloaded, i.e., PEM-encoded.
+    ///
+    /// [RFC 8555 §7.4.2]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.4.2
+    pub async fn all_certificate_chains(&self) -> eyre::Result<Vec<(Option<String>, Vec<u8>)>> {
+        let url = self
+            .order
+            .api_order
+            .certificate
+            .as_deref()
+            .ok_or_else(|| eyre::eyre!("certificate url"))?;
+
+        let transport = &self.order.acc.transport;
+
+        let res = transport.call_kid(url, &api::EmptyString).await?;
+        let alternates = req_links(&res, "alternate");
+
+        let mut chains = vec![(None, req_bytes(res).await?)];
+
+        for alternate in alternates {
+            let res = transport.call_kid(&alternate, &api::EmptyString).await?;
+            let chain = req_bytes(res).await?;
+            chains.push((Some(alternate), chain));
+        }
+
+        Ok(chains)
+    }
+
+    /// Downloads the first certificate chain whose topmost certificate was issued by
+    /// `issuer_common_name`, falling back to the default chain if none match.
+    ///
+    /// Useful for picking a shorter or cross-signed chain, like Certbot's `--preferred-chain`
+    /// option. Chains are considered in the order returned by
+    /// [`all_certificate_chains()`](Self::all_certificate_chains) and returned PEM-encoded.
+    pub async fn preferred_certificate_chain(
+        &self,
+        issuer_common_name: &str,
+    ) -> eyre::Result<Vec<u8>> {
+        let mut chains = self.all_certificate_chains().await?;
+
+        let preferred = chains.iter().position(|(_, chain)| {
+            let mut rdr = std::io::Cursor::new(chain);
+
+            // chains that fail to parse never match
+            let Some(Ok(top)) = rustls_pemfile::certs(&mut rdr).last() else {
+                return false;
+            };
+
+            crate::util::certificate_issuer_common_name(&top)
+                .is_ok_and(|cn| cn.as_deref() == Some(issuer_common_name))
+        });
+
+        Ok(chains.swap_remove(preferred.unwrap_or(0)).1)
+    }
+
     /// Returns a reference to the order's API object.
     ///
     /// Useful for debugging.
@@ -306,6 +865,56 @@ mod tests {
     use super::*;
     use crate::{cert, Directory, DirectoryUrl};
 
+    #[tokio::test]
+    async fn test_check_resumable() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let inner = &ord.order.acc;
+        let url = ord.order.url.clone();
+
+        let order = refresh_order(inner, url.clone(), "valid").await.unwrap();
+        order.check_resumable().unwrap();
+
+        // test order expired in 2019
+        let order = refresh_order(inner, url.clone(), "ready").await.unwrap();
+        let err = order.check_resumable().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderExpired { .. }),
+        ));
+
+        let order = refresh_order(inner, url, "invalid").await.unwrap();
+        let err = order.check_resumable().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderInvalid { .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let mut ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        assert_eq!(ord.status(), Some(api::OrderStatus::Pending));
+        assert_eq!(ord.certificate_url(), None);
+
+        ord.refresh().await.unwrap();
+        assert_eq!(ord.status(), Some(api::OrderStatus::Ready));
+        assert!(ord
+            .certificate_url()
+            .unwrap()
+            .ends_with("/acme/cert/fae41c070f967713109028"));
+    }
+
     #[tokio::test]
     async fn test_get_authorizations() {
         let server = crate::test::with_directory_server();
@@ -319,6 +928,185 @@ mod tests {
         let _authorizations = ord.authorizations().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_authorization_counts() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        assert_eq!(
+            ord.authorization_counts().await.unwrap(),
+            AuthorizationCounts {
+                reused: 0,
+                required: 1,
+                failed: 0,
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authorization_counts_failed() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-invalid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        assert_eq!(
+            ord.authorization_counts().await.unwrap(),
+            AuthorizationCounts {
+                reused: 0,
+                required: 0,
+                failed: 1,
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_finalize_if_ready_requires_challenges() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let err = ord
+            .try_finalize_if_ready(cert::create_p256_key(), &PollConfig::single_attempt())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::ChallengesRequired { domains }) if domains == &["acmetest.algesten.se"],
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_try_finalize_if_ready_exhausts_poll_attempts() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/authz/", "/acme/authz-valid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let err = ord
+            .try_finalize_if_ready(cert::create_p256_key(), &PollConfig::single_attempt())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::PollAttemptsExhausted { attempts: 1, .. }),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_try_finalize_if_ready_bounds_finalization() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| {
+                url.replace("/acme/authz/", "/acme/authz-valid/")
+                    .replace("/acme/order/", "/acme/order-processing/")
+            })
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        // the order becomes ready, then never leaves processing once finalized
+        let poll_config = PollConfig::new(Duration::ZERO).with_max_attempts(2);
+        let err = ord
+            .try_finalize_if_ready(cert::create_p256_key(), &poll_config)
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::PollAttemptsExhausted { attempts: 2, .. }),
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_order_finalization_deadline() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        // without timers of its own, the client does not let the paused clock skip ahead
+        let http_client = reqwest::Client::builder()
+            .pool_idle_timeout(None)
+            .build()
+            .unwrap();
+        let dir = Directory::builder(url)
+            .http_client(http_client)
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        let stuck_url = ord.order.url.replace("/acme/order/", "/acme/order-stuck/");
+
+        // the order never leaves processing and the poll config has no attempt limit; polled after
+        // 0, 20, 40, and 50 seconds
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(50);
+        let err = poll_order_finalization(
+            &ord.order.acc,
+            &stuck_url,
+            &PollConfig::new(Duration::from_secs(20)),
+            Some(deadline),
+        )
+        .await
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::FinalizeTimeout { url, attempts: 4 }) if url == &stuck_url,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_http_proofs() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        let proofs = ord.http_proofs().await.unwrap();
+        let token = "MUi-gqeOJdRkSb_YR2eaMxQBqf6al8dgt_dOttSWb0w";
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(
+            proofs[token],
+            format!("{token}.{}", acc.thumbprint().unwrap())
+        );
+    }
+
     #[tokio::test]
     async fn test_finalize() {
         let server = crate::test::with_directory_server();
@@ -338,6 +1126,171 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_finalize_with_csr_params() {
+        use der::Decode as _;
+
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let params = CsrParams::new().with_organization("Example Inc");
+        let ord = ord
+            .finalize_with_csr_params(private_key, Duration::from_millis(1), &params)
+            .await
+            .unwrap();
+
+        let csr = x509_cert::request::CertReq::from_der(ord.csr_der()).unwrap();
+        assert_eq!(
+            csr.info.subject.to_string(),
+            "CN=acme-test.example.com,O=Example Inc",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csr_der() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let params = CsrParams::new();
+
+        let csr_der = ord.csr_der(&private_key, &params).unwrap();
+        let ord = ord
+            .finalize_with_csr_params(private_key, Duration::from_millis(1), &params)
+            .await
+            .unwrap();
+
+        assert_eq!(ord.csr_der(), csr_der);
+        assert!(ord
+            .csr_pem()
+            .unwrap()
+            .starts_with("-----BEGIN CERTIFICATE REQUEST-----"));
+    }
+
+    #[tokio::test]
+    async fn test_finalize_and_wait() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let cert = ord
+            .finalize_and_wait(private_key, &CsrParams::new(), Duration::from_millis(1))
+            .await
+            .unwrap();
+        assert!(!cert.certificate().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finalize_and_wait_invalid() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::builder(url)
+            .rewrite_url(|url| url.replace("/acme/order/", "/acme/order-invalid/"))
+            .fetch()
+            .await
+            .unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let err = ord
+            .finalize_and_wait(private_key, &CsrParams::new(), Duration::from_millis(1))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderInvalid { problem: Some(problem), .. })
+                if problem.problem_type() == Some(api::ProblemType::BadCsr),
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_finalize_with_der_csr() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let private_key = cert::create_p256_key();
+
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let csr = create_csr(&private_key, &["other.example.com"], &CsrParams::new()).unwrap();
+        let Err(err) = ord
+            .finalize_with_der_csr(&csr.to_der().unwrap(), Duration::from_millis(1))
+            .await
+        else {
+            panic!("finalizing with a mismatched CSR should fail");
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::CsrIdentifiersMismatch { .. }),
+        ));
+
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let csr = create_csr(&private_key, &["acme-test.example.com"], &CsrParams::new()).unwrap();
+        let csr_der = csr.to_der().unwrap();
+        let ord = ord
+            .finalize_with_der_csr(&csr_der, Duration::from_millis(1))
+            .await
+            .unwrap();
+        assert_eq!(ord.csr_der(), csr_der);
+
+        assert_eq!(ord.all_certificate_chains().await.unwrap().len(), 2);
+        assert!(matches!(
+            ord.private_key_pem().unwrap_err().downcast_ref::<Error>(),
+            Some(Error::PrivateKeyUnknown { .. }),
+        ));
+        assert!(ord.download_cert().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_finalize_with_margin_expiring() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        // shortcut auth; test order expired in 2019
+        let ord = CsrOrder { order: ord.order };
+        let err = ord
+            .finalize_with_margin(
+                cert::create_p256_key(),
+                Duration::from_millis(1),
+                Duration::from_secs(60),
+            )
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::OrderExpiring { expires, .. }) if expires == "2019-01-09T08:26:43.570360537Z",
+        ));
+    }
+
     #[tokio::test]
     async fn test_download_and_save_cert() {
         let server = crate::test::with_directory_server();
@@ -362,4 +1315,104 @@ mod tests {
         assert!(!cert.private_key().is_empty());
         assert_eq!(cert.valid_days_left().unwrap(), 89);
     }
+
+    #[tokio::test]
+    async fn test_private_key_pem() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir.register_account(None).await.unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let expected = private_key.to_pkcs8_pem(der::pem::LineEnding::LF).unwrap();
+        let ord = ord
+            .finalize(private_key, Duration::from_millis(1))
+            .await
+            .unwrap();
+
+        let private_key_pem = ord.private_key_pem().unwrap();
+        assert_eq!(private_key_pem, expected);
+
+        let cert = ord.download_cert().await.unwrap();
+        assert_eq!(cert.private_key(), private_key_pem.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_all_certificate_chains() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let ord = ord
+            .finalize(private_key, Duration::from_millis(1))
+            .await
+            .unwrap();
+
+        let chains = ord.all_certificate_chains().await.unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0], (None, b"CERT HERE".to_vec()));
+        assert!(chains[1]
+            .0
+            .as_deref()
+            .unwrap()
+            .ends_with("/acme/cert/fae41c070f967713109028/1"));
+        assert!(chains[1].1.starts_with(b"-----BEGIN CERTIFICATE-----"));
+
+        let chain = ord
+            .preferred_certificate_chain(crate::test::ALTERNATE_ROOT)
+            .await
+            .unwrap();
+        assert!(chain.starts_with(b"-----BEGIN CERTIFICATE-----"));
+
+        let chain = ord
+            .preferred_certificate_chain("Unknown Root")
+            .await
+            .unwrap();
+        assert_eq!(chain, b"CERT HERE");
+    }
+
+    #[tokio::test]
+    async fn test_download_cert_with_retry_unavailable() {
+        let server = crate::test::with_directory_server();
+        let url = DirectoryUrl::Other(&server.dir_url);
+        let dir = Directory::fetch(url).await.unwrap();
+        let acc = dir
+            .register_account(Some(vec!["mailto:foo@bar.com".to_owned()]))
+            .await
+            .unwrap();
+        let ord = acc.new_order("acme-test.example.com", &[]).await.unwrap();
+
+        // shortcut auth
+        let ord = CsrOrder { order: ord.order };
+        let private_key = cert::create_p256_key();
+        let mut ord = ord
+            .finalize(private_key, Duration::from_millis(1))
+            .await
+            .unwrap();
+
+        let cert_url = server.dir_url.replace("/directory", "/acme/cert/missing");
+        ord.order.api_order.certificate = Some(cert_url.clone());
+
+        let err = ord
+            .download_cert_with_retry(3, Duration::from_millis(1))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::CertificateUnavailable { url, attempts: 3 }) if url == &cert_url,
+        ));
+    }
 }
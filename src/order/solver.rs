@@ -0,0 +1,238 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::Error;
+
+/// Future returned by solver methods.
+pub type SolverFuture<'a> = Pin<Box<dyn Future<Output = eyre::Result<()>> + Send + 'a>>;
+
+/// Publishes and removes `TXT` records for `dns-01` challenges.
+///
+/// Used with [`Auth::solve_dns()`], which guarantees that [`remove_record()`] is called after
+/// [`set_record()`], even if validation fails.
+///
+/// ```
+/// use acme::order::{DnsSolver, SolverFuture};
+///
+/// struct PrintSolver;
+///
+/// impl DnsSolver for PrintSolver {
+///     fn set_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a> {
+///         Box::pin(async move {
+///             println!("create: {record_name} TXT {value}");
+///             Ok(())
+///         })
+///     }
+///
+///     fn remove_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a> {
+///         Box::pin(async move {
+///             println!("remove: {record_name} TXT {value}");
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+///
+/// [`Auth::solve_dns()`]: crate::order::Auth::solve_dns()
+/// [`set_record()`]: Self::set_record()
+/// [`remove_record()`]: Self::remove_record()
+pub trait DnsSolver: Send + Sync {
+    /// Creates a `TXT` record named `record_name` containing `value`.
+    ///
+    /// The record name does not have a trailing dot, e.g. `_acme-challenge.example.org`.
+    fn set_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a>;
+
+    /// Removes the `TXT` record named `record_name` containing `value`.
+    fn remove_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a>;
+}
+
+/// DNS solver that does nothing, for records that are managed elsewhere.
+///
+/// Useful with [`Auth::solve_dns()`] when the `TXT` record has been published out-of-band, e.g.,
+/// using [`Challenge<Dns>::delegated_record_name()`] and a separate provisioning step.
+///
+/// [`Auth::solve_dns()`]: crate::order::Auth::solve_dns()
+/// [`Challenge<Dns>::delegated_record_name()`]: crate::order::Challenge::delegated_record_name()
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopSolver;
+
+impl DnsSolver for NoopSolver {
+    fn set_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove_record<'a>(&'a self, _record_name: &'a str, _value: &'a str) -> SolverFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// DNS solver that logs instructions for creating and removing `TXT` records by hand.
+///
+/// The instructions are logged at the info level. After logging the record to create, waits for the configured duration to give the user time
+/// to create it and for it to propagate.
+#[derive(Debug, Clone)]
+pub struct ManualSolver {
+    wait: Duration,
+}
+
+impl ManualSolver {
+    /// Constructs new manual solver which waits for `wait` after logging the record to create.
+    pub const fn new(wait: Duration) -> Self {
+        Self { wait }
+    }
+
+    /// Returns the time waited after logging the record to create.
+    pub const fn wait(&self) -> Duration {
+        self.wait
+    }
+}
+
+impl DnsSolver for ManualSolver {
+    fn set_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a> {
+        Box::pin(async move {
+            log::info!("Create the following DNS record: {record_name}.  TXT  \"{value}\"");
+            log::info!("Waiting {:?} before validating...", self.wait);
+
+            tokio::time::sleep(self.wait).await;
+
+            Ok(())
+        })
+    }
+
+    fn remove_record<'a>(&'a self, record_name: &'a str, value: &'a str) -> SolverFuture<'a> {
+        Box::pin(async move {
+            log::info!(
+                "The following DNS record can now be removed: {record_name}.  TXT  \"{value}\""
+            );
+
+            Ok(())
+        })
+    }
+}
+
+/// Publishes and removes proofs for `http-01` challenges.
+///
+/// Used with [`Auth::solve_http()`], which guarantees that [`unpublish()`] is called after
+/// [`publish()`], even if validation fails.
+///
+/// [`Auth::solve_http()`]: crate::order::Auth::solve_http()
+/// [`publish()`]: Self::publish()
+/// [`unpublish()`]: Self::unpublish()
+pub trait HttpSolver: Send + Sync {
+    /// Serves `proof` for `domain` under `/.well-known/acme-challenge/<token>`.
+    fn publish<'a>(&'a self, domain: &'a str, token: &'a str, proof: &'a str) -> SolverFuture<'a>;
+
+    /// Stops serving the proof for `token`.
+    fn unpublish<'a>(&'a self, domain: &'a str, token: &'a str) -> SolverFuture<'a>;
+}
+
+/// Future returned by [`TxtResolver::lookup_txt()`].
+pub type TxtLookupFuture<'a> = Pin<Box<dyn Future<Output = eyre::Result<Vec<String>>> + Send + 'a>>;
+
+/// Future returned by [`TxtResolver::lookup_cname()`].
+pub type CnameLookupFuture<'a> =
+    Pin<Box<dyn Future<Output = eyre::Result<Option<String>>> + Send + 'a>>;
+
+/// Looks up `TXT` records, e.g., for checking `dns-01` challenge proofs before validation.
+///
+/// Used with the `self_check()` method of [`Challenge<Dns>`]. Implementations can query the
+/// domain's authoritative nameservers directly to avoid waiting for caches to expire.
+///
+/// [`Challenge<Dns>`]: crate::order::Challenge
+pub trait TxtResolver: Send + Sync {
+    /// Returns the values of all `TXT` records named `record_name`.
+    ///
+    /// The record name does not have a trailing dot, e.g. `_acme-challenge.example.org`. Returns
+    /// an empty list if no such records exist.
+    fn lookup_txt<'a>(&'a self, record_name: &'a str) -> TxtLookupFuture<'a>;
+
+    /// Returns the target of the `CNAME` record named `name`, if any.
+    ///
+    /// Used by [`Challenge<Dns>::delegated_record_name()`]. The target may have a trailing dot.
+    /// Returns `None` only if no such record exists, since the delegation would otherwise be
+    /// missed.
+    ///
+    /// [`Challenge<Dns>::delegated_record_name()`]: crate::order::Challenge::delegated_record_name()
+    fn lookup_cname<'a>(&'a self, name: &'a str) -> CnameLookupFuture<'a>;
+}
+
+/// Future which catches panics while polling the wrapped future.
+///
+/// Used by solver methods so that cleanup runs before the panic is resumed. Cleanup is async, so
+/// it cannot run in a drop guard.
+pub(crate) struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> CatchUnwind<F> {
+    pub(crate) fn new(fut: F) -> Self {
+        Self(Box::pin(fut))
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fut = self.0.as_mut();
+
+        match panic::catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+/// Combines the result of solving a challenge with the result of cleaning up after it.
+pub(crate) fn with_cleanup(res: eyre::Result<()>, cleanup: eyre::Result<()>) -> eyre::Result<()> {
+    match (res, cleanup) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Ok(()), Err(cleanup)) => Err(cleanup),
+        (Err(error), Ok(())) => Err(error),
+        (Err(error), Err(cleanup)) => Err(Error::Cleanup { error, cleanup }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_noop_solver() {
+        let solver = NoopSolver;
+
+        solver
+            .set_record("_acme-challenge.example.org", "value")
+            .await
+            .unwrap();
+        solver
+            .remove_record("_acme-challenge.example.org", "value")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_manual_solver_waits() {
+        let solver = ManualSolver::new(Duration::from_secs(60));
+        assert_eq!(solver.wait(), Duration::from_secs(60));
+
+        let start = tokio::time::Instant::now();
+        solver
+            .set_record("_acme-challenge.example.org", "value")
+            .await
+            .unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+
+        let start = tokio::time::Instant::now();
+        solver
+            .remove_record("_acme-challenge.example.org", "value")
+            .await
+            .unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::RwLock;
+
+use crate::order::{HttpSolver, SolverFuture};
+
+/// In-memory store of `http-01` challenge proofs, keyed by token.
+///
+/// Lets servers which are already serving traffic answer challenges without writing files. Mount
+/// a handler for `/.well-known/acme-challenge/<token>` which looks up the proof using
+/// [`respond()`](Self::respond) and serves it as the response body, or responds with
+/// `404 Not Found` if there is none.
+///
+/// Proofs are added using [`Challenge<Http>::register()`] or by using the store as an
+/// [`HttpSolver`] with [`Auth::solve_http()`]. Clones share the same proofs.
+///
+/// [`Challenge<Http>::register()`]: crate::order::Challenge::register()
+/// [`Auth::solve_http()`]: crate::order::Auth::solve_http()
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeStore {
+    proofs: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl ChallengeStore {
+    /// Constructs new, empty challenge store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `proof` to be served for `token`.
+    pub fn insert(&self, token: impl Into<String>, proof: impl Into<String>) {
+        self.proofs.write().insert(token.into(), proof.into());
+    }
+
+    /// Removes the proof for `token`, returning it if it was stored.
+    pub fn remove(&self, token: &str) -> Option<String> {
+        self.proofs.write().remove(token)
+    }
+
+    /// Returns the proof to serve for `token`, if any.
+    pub fn respond(&self, token: &str) -> Option<String> {
+        self.proofs.read().get(token).cloned()
+    }
+}
+
+impl HttpSolver for ChallengeStore {
+    fn publish<'a>(&'a self, _domain: &'a str, token: &'a str, proof: &'a str) -> SolverFuture<'a> {
+        self.insert(token, proof);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn unpublish<'a>(&'a self, _domain: &'a str, token: &'a str) -> SolverFuture<'a> {
+        self.remove(token);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_challenge_store() {
+        let store = ChallengeStore::new();
+        assert_eq!(store.respond("token"), None);
+
+        store
+            .publish("example.org", "token", "proof")
+            .await
+            .unwrap();
+        assert_eq!(store.clone().respond("token").as_deref(), Some("proof"));
+
+        store.unpublish("example.org", "token").await.unwrap();
+        assert_eq!(store.respond("token"), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+/// Maximum delay between poll attempts requested by the ACME API's `Retry-After` header that is
+/// honored.
+pub(crate) const MAX_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Configuration for polling the ACME API while waiting for a status change.
+///
+/// Different ACME API providers have very different validation latencies, so a [`Directory`]
+/// carries a default configuration (see [`DirectoryBuilder::poll_config()`]) which is used by
+/// methods such as [`Challenge::validate_default()`].
+///
+/// Delays requested by the ACME API's `Retry-After` header take precedence over the interval, but
+/// are capped at 5 minutes.
+///
+/// [`Directory`]: crate::Directory
+/// [`DirectoryBuilder::poll_config()`]: crate::DirectoryBuilder::poll_config()
+/// [`Challenge::validate_default()`]: crate::order::Challenge::validate_default()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollConfig {
+    interval: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl PollConfig {
+    /// Constructs new poll configuration which waits `interval` between each poll attempt.
+    ///
+    /// Polls indefinitely until the status changes.
+    pub const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_attempts: None,
+        }
+    }
+
+    /// Preset which makes exactly one poll attempt, without sleeping.
+    ///
+    /// Polling fails immediately with [`Error::PollAttemptsExhausted`] if the status has not
+    /// changed yet. Useful for fast, deterministic tests of failure paths.
+    ///
+    /// [`Error::PollAttemptsExhausted`]: crate::Error::PollAttemptsExhausted
+    pub const fn single_attempt() -> Self {
+        Self::new(Duration::ZERO).with_max_attempts(1)
+    }
+
+    /// Limits the number of poll attempts to `max_attempts`.
+    pub const fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Preset suitable for Let's Encrypt.
+    ///
+    /// Polls every 5 seconds.
+    pub const fn lets_encrypt() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+
+    /// Preset suitable for [step-ca], which validates almost instantly.
+    ///
+    /// Polls every 250 milliseconds.
+    ///
+    /// [step-ca]: https://smallstep.com/docs/step-ca
+    pub const fn step_ca() -> Self {
+        Self::new(Duration::from_millis(250))
+    }
+
+    /// Returns the amount of time to wait between each poll attempt.
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the maximum number of poll attempts, if limited.
+    pub const fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// Returns true if no more attempts should be made after `attempts` attempts.
+    pub(crate) fn is_exhausted(&self, attempts: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+    }
+
+    /// Returns the delay before the next poll attempt: the delay requested by the ACME API's
+    /// `Retry-After` header, capped at [`MAX_RETRY_AFTER`], or the interval if absent.
+    ///
+    /// The delay is also capped at the time remaining until `deadline`. Returns `None` if the
+    /// deadline has passed.
+    pub(crate) fn next_delay(
+        &self,
+        retry_after: Option<Duration>,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Option<Duration> {
+        let delay = retry_after.map_or(self.interval, |delay| delay.min(MAX_RETRY_AFTER));
+
+        match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                (!remaining.is_zero()).then(|| delay.min(remaining))
+            }
+            None => Some(delay),
+        }
+    }
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self::lets_encrypt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_delay() {
+        let poll_config = PollConfig::new(Duration::from_secs(2));
+        let now = tokio::time::Instant::now();
+
+        assert_eq!(
+            poll_config.next_delay(None, None),
+            Some(Duration::from_secs(2)),
+        );
+        assert_eq!(
+            poll_config.next_delay(Some(Duration::from_secs(10)), None),
+            Some(Duration::from_secs(10)),
+        );
+
+        // excessive Retry-After delays are capped
+        assert_eq!(
+            poll_config.next_delay(Some(Duration::from_secs(60 * 60 * 24)), None),
+            Some(MAX_RETRY_AFTER),
+        );
+
+        // and so are delays past the deadline
+        let delay = poll_config
+            .next_delay(
+                Some(Duration::from_secs(60)),
+                Some(now + Duration::from_secs(30)),
+            )
+            .unwrap();
+        assert!(delay <= Duration::from_secs(30));
+
+        assert_eq!(poll_config.next_delay(None, Some(now)), None);
+    }
+}
@@ -1,38 +1,143 @@
 use std::time::Duration;
 
-use crate::api::Problem;
+use reqwest::{
+    header::{HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, HOST},
+    Method,
+};
+use serde::de::DeserializeOwned;
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+use crate::{api::Problem, http::HttpClient, Error};
 
 pub(crate) type ReqResult<T> = std::result::Result<T, Problem>;
 
-pub(crate) async fn req_get(url: &str) -> reqwest::Response {
-    let client = http_client();
-    let req = client.get(url);
+/// Default time limit for establishing a connection to the ACME API.
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time limit for each request to the ACME API, from connecting until the response body has
+/// been read.
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(crate) async fn req_get(client: &HttpClient, url: &str) -> eyre::Result<reqwest::Response> {
+    let req = req_new(Method::GET, url)?;
     log::trace!("{req:?}");
-    req.send().await.unwrap()
+    req_send(client, req, url).await
 }
 
-pub(crate) async fn req_head(url: &str) -> reqwest::Response {
-    let client = http_client();
-    let req = client.head(url).header("cache-control", "no-store");
+/// Sends a GET request to `url` with its `Host` header set to `host`.
+pub(crate) async fn req_get_with_host(
+    client: &HttpClient,
+    url: &str,
+    host: &str,
+) -> eyre::Result<reqwest::Response> {
+    let mut req = req_new(Method::GET, url)?;
+    req.headers_mut().insert(HOST, HeaderValue::try_from(host)?);
     log::trace!("{req:?}");
-    req.send().await.unwrap()
+    req_send(client, req, url).await
 }
 
-fn http_client() -> reqwest::Client {
+pub(crate) async fn req_head(client: &HttpClient, url: &str) -> eyre::Result<reqwest::Response> {
+    let mut req = req_new(Method::HEAD, url)?;
+    req.headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    log::trace!("{req:?}");
+    req_send(client, req, url).await
+}
+
+/// Returns the HTTP client used unless one is configured with
+/// [`DirectoryBuilder::http_client()`](crate::DirectoryBuilder::http_client()).
+pub(crate) fn http_client(connect_timeout: Duration, request_timeout: Duration) -> reqwest::Client {
     reqwest::ClientBuilder::new()
-        .connect_timeout(Duration::from_secs(30))
-        .timeout(Duration::from_secs(30))
+        .connect_timeout(connect_timeout)
+        .timeout(request_timeout)
         .build()
         .unwrap()
 }
 
-pub(crate) async fn req_post(url: &str, body: &str) -> reqwest::Response {
-    let client = http_client();
-    let req = client
-        .post(url)
-        .header("content-type", "application/jose+json");
-    log::trace!("{req:?} {body}");
-    req.body(body.to_owned()).send().await.unwrap()
+pub(crate) async fn req_post(
+    client: &HttpClient,
+    url: &str,
+    body: &str,
+    headers: &[(&str, &str)],
+) -> eyre::Result<reqwest::Response> {
+    let mut req = req_new(Method::POST, url)?;
+    req.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/jose+json"),
+    );
+    for (name, value) in headers {
+        req.headers_mut()
+            .insert(HeaderName::try_from(*name)?, HeaderValue::try_from(*value)?);
+    }
+    // the body is a signed JWS, so only its length is logged
+    log::trace!("POST {url} ({} bytes)", body.len());
+    *req.body_mut() = Some(body.to_owned().into());
+    req_send(client, req, url).await
+}
+
+fn req_new(method: Method, url: &str) -> eyre::Result<reqwest::Request> {
+    let parsed = url
+        .parse()
+        .map_err(|err| eyre::eyre!("Invalid URL {url}: {err}"))?;
+    Ok(reqwest::Request::new(method, parsed))
+}
+
+async fn req_send(
+    client: &HttpClient,
+    req: reqwest::Request,
+    url: &str,
+) -> eyre::Result<reqwest::Response> {
+    client
+        .execute(req)
+        .await
+        .map_err(|err| match err.downcast::<reqwest::Error>() {
+            Ok(err) => req_send_error(url, err),
+            Err(err) => err,
+        })
+}
+
+/// Converts a failure to send a request to `url`, distinguishing timeouts as [`Error::Timeout`].
+fn req_send_error(url: &str, err: reqwest::Error) -> eyre::Report {
+    if err.is_timeout() {
+        Error::Timeout {
+            url: url.to_owned(),
+        }
+        .into()
+    } else {
+        eyre::Report::new(err).wrap_err(format!("Request to {url} failed"))
+    }
+}
+
+/// Reads the body of `res` as JSON, distinguishing timeouts as [`Error::Timeout`].
+pub(crate) async fn req_json<T: DeserializeOwned>(res: reqwest::Response) -> eyre::Result<T> {
+    let url = res.url().to_string();
+    res.json().await.map_err(|err| req_body_error(&url, err))
+}
+
+/// Reads the body of `res` as text, distinguishing timeouts as [`Error::Timeout`].
+pub(crate) async fn req_text(res: reqwest::Response) -> eyre::Result<String> {
+    let url = res.url().to_string();
+    res.text().await.map_err(|err| req_body_error(&url, err))
+}
+
+/// Reads the body of `res` as bytes, distinguishing timeouts as [`Error::Timeout`].
+pub(crate) async fn req_bytes(res: reqwest::Response) -> eyre::Result<Vec<u8>> {
+    let url = res.url().to_string();
+    let body = res.bytes().await.map_err(|err| req_body_error(&url, err))?;
+    Ok(body.to_vec())
+}
+
+/// Converts a failure to read the response body of a request to `url`, distinguishing timeouts as
+/// [`Error::Timeout`].
+pub(crate) fn req_body_error(url: &str, err: reqwest::Error) -> eyre::Report {
+    if err.is_timeout() {
+        Error::Timeout {
+            url: url.to_owned(),
+        }
+        .into()
+    } else {
+        err.into()
+    }
 }
 
 pub(crate) async fn req_handle_error(res: reqwest::Response) -> ReqResult<reqwest::Response> {
@@ -41,7 +146,14 @@ pub(crate) async fn req_handle_error(res: reqwest::Response) -> ReqResult<reqwes
         return Ok(res);
     }
 
-    let problem = if res.headers().get("content-type").unwrap() == "application/problem+json" {
+    let http_status = res.status().as_u16();
+
+    let is_problem_json = res
+        .headers()
+        .get("content-type")
+        .is_some_and(|content_type| content_type == "application/problem+json");
+
+    let mut problem = if is_problem_json {
         // if we were sent a problem+json, deserialize it
         let body = res.text().await.unwrap();
 
@@ -52,7 +164,7 @@ pub(crate) async fn req_handle_error(res: reqwest::Response) -> ReqResult<reqwes
             detail: Some(format!(
                 "Failed to deserialize application/problem+json ({err}) body: {body}"
             )),
-            subproblems: None,
+            ..Default::default()
         })
     } else {
         // some other problem
@@ -62,20 +174,113 @@ pub(crate) async fn req_handle_error(res: reqwest::Response) -> ReqResult<reqwes
         Problem {
             _type: "httpReqError".to_owned(),
             detail: Some(detail),
-            subproblems: None,
+            ..Default::default()
         }
     };
 
+    // the problem document's status field is optional
+    problem.status.get_or_insert(http_status);
+
     Err(problem)
 }
 
-pub(crate) fn req_expect_header(res: &reqwest::Response, name: &str) -> ReqResult<String> {
+/// Returns the targets of `Link` headers with relation type `rel`.
+///
+/// See [RFC 8288 §3].
+///
+/// [RFC 8288 §3]: https://datatracker.ietf.org/doc/html/rfc8288#section-3
+pub(crate) fn req_links(res: &reqwest::Response, rel: &str) -> Vec<String> {
     res.headers()
-        .get(name)
-        .map(|v| v.to_str().unwrap().to_owned())
-        .ok_or_else(|| Problem {
-            _type: format!("Missing header: {name}"),
-            detail: None,
-            subproblems: None,
+        .get_all("link")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|link| {
+            let (target, params) = link.trim().split_once(';')?;
+
+            let has_rel = params.split(';').any(|param| {
+                param
+                    .trim()
+                    .split_once('=')
+                    .is_some_and(|(name, value)| name == "rel" && value.trim_matches('"') == rel)
+            });
+
+            has_rel.then(|| {
+                target
+                    .trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_owned()
+            })
         })
+        .collect()
+}
+
+/// Returns how long the `Retry-After` header asks to wait before the next request, if present.
+pub(crate) fn req_retry_after(res: &reqwest::Response) -> Option<Duration> {
+    let value = res.headers().get("retry-after")?.to_str().ok()?;
+    parse_retry_after(value, OffsetDateTime::now_utc())
+}
+
+/// Parses a `Retry-After` header value, either in delay-seconds or HTTP-date form.
+///
+/// HTTP dates in the past result in a zero delay. Returns `None` for malformed values.
+///
+/// See [RFC 9110 §10.2.3].
+///
+/// [RFC 9110 §10.2.3]: https://datatracker.ietf.org/doc/html/rfc9110#section-10.2.3
+fn parse_retry_after(value: &str, now: OffsetDateTime) -> Option<Duration> {
+    let value = value.trim();
+
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return value.parse().ok().map(Duration::from_secs);
+    }
+
+    let date = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    Some((date - now).try_into().unwrap_or(Duration::ZERO))
+}
+
+pub(crate) fn req_expect_header(res: &reqwest::Response, name: &str) -> eyre::Result<String> {
+    let value = res
+        .headers()
+        .get(name)
+        .ok_or_else(|| eyre::eyre!("Missing header: {name}"))?;
+
+    Ok(value
+        .to_str()
+        .map_err(|err| eyre::eyre!("Invalid header {name}: {err}"))?
+        .to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::format_description::well_known::Rfc3339;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = OffsetDateTime::parse("2015-10-21T07:27:00Z", &Rfc3339).unwrap();
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120)),
+        );
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(60)),
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:26:00 GMT", now),
+            Some(Duration::ZERO),
+        );
+
+        assert_eq!(parse_retry_after("", now), None);
+        assert_eq!(parse_retry_after("-5", now), None);
+        assert_eq!(parse_retry_after("1.5", now), None);
+        assert_eq!(parse_retry_after("99999999999999999999999", now), None);
+        assert_eq!(parse_retry_after("tomorrow", now), None);
+    }
 }
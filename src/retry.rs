@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use rand::Rng as _;
+
+/// Configuration for retrying requests rejected by the ACME API's rate limits.
+///
+/// Requests failing with a `rateLimited` problem or HTTP status `429 Too Many Requests` are retried
+/// after the delay given by the response's `Retry-After` header, if any, up to the maximum backoff.
+/// Otherwise, the delay grows exponentially with each retry, with random jitter, up to that maximum.
+///
+/// Retrying is opt-in; see [`DirectoryBuilder::rate_limit_retry()`].
+///
+/// [`DirectoryBuilder::rate_limit_retry()`]: crate::DirectoryBuilder::rate_limit_retry()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitRetry {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RateLimitRetry {
+    /// Constructs new rate limit retry configuration which retries each request up to
+    /// `max_retries` times.
+    ///
+    /// Backs off for 1 second before the first retry, doubling up to 1 minute.
+    pub const fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets the delay before the first retry, when not given by the ACME API.
+    pub const fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries, also capping delays given by the ACME API.
+    pub const fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Returns the maximum number of times each request is retried.
+    pub const fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the delay before the first retry, when not given by the ACME API.
+    pub const fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// Returns the maximum delay between retries, also capping delays given by the ACME API.
+    pub const fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// Returns the delay before retry number `retry` (starting at 1), with jitter.
+    ///
+    /// The delay is between half and all of the exponential backoff, capped at the maximum.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    /// Returns the delay before retry number `retry`, preferring the `retry_after` delay requested
+    /// by the ACME API, capped at the maximum backoff.
+    pub(crate) fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.map_or_else(|| self.backoff(retry), |delay| delay.min(self.max_backoff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let retry = RateLimitRetry::new(10)
+            .with_initial_backoff(Duration::from_secs(2))
+            .with_max_backoff(Duration::from_secs(10));
+
+        for (n, max) in [(1, 2), (2, 4), (3, 8), (4, 10), (10, 10)] {
+            let backoff = retry.backoff(n);
+            let max = Duration::from_secs(max);
+
+            assert!(backoff >= max / 2, "retry {n}: {backoff:?}");
+            assert!(backoff <= max, "retry {n}: {backoff:?}");
+        }
+    }
+
+    #[test]
+    fn test_delay_caps_retry_after() {
+        let retry = RateLimitRetry::new(10).with_max_backoff(Duration::from_secs(10));
+
+        assert_eq!(
+            retry.delay(1, Some(Duration::from_secs(3))),
+            Duration::from_secs(3),
+        );
+        assert_eq!(
+            retry.delay(1, Some(Duration::from_secs(86400))),
+            Duration::from_secs(10),
+        );
+        assert!(retry.delay(1, None) <= Duration::from_secs(1));
+    }
+}
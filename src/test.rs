@@ -1,10 +1,18 @@
 #![allow(clippy::trivial_regex)]
 
-use std::{convert::Infallible, future::ready, net::TcpListener, sync::OnceLock};
+use std::{
+    convert::Infallible,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        OnceLock,
+    },
+};
 
-use actix_http::{HttpService, Method, Request, Response, StatusCode};
+use actix_http::{HttpMessage as _, HttpService, Method, Request, Response, StatusCode};
 use actix_server::{Server, ServerHandle};
 use actix_web::body::MessageBody;
+use futures_util::StreamExt as _;
 use regex::Regex;
 
 static RE_URL: OnceLock<Regex> = OnceLock::new();
@@ -32,6 +40,7 @@ fn get_directory(url: &str) -> Response<impl MessageBody> {
     "newOrder": "<URL>/acme/new-order",
     "revokeCert": "<URL>/acme/revoke-cert",
     "meta": {
+        "termsOfService": "<URL>/terms",
         "caaIdentities": [
         "testdir.org"
         ]
@@ -46,6 +55,39 @@ fn get_directory(url: &str) -> Response<impl MessageBody> {
     )
 }
 
+/// Issuer common name of the alternate certificate chain.
+pub const ALTERNATE_ROOT: &str = "Alternate Root X1";
+
+pub const TERMS: &str = "Terms of service.";
+
+/// `Idempotency-Key` header value required by `/acme/new-order-idempotent`.
+pub const IDEMPOTENCY_KEY: &str = "7ZAtrnQmFEz6v1Bp7pMddA";
+
+/// Self-signed certificate for `fixture.example.com` with serial number `01ABCD`, generated with
+/// OpenSSL.
+pub const FIXTURE_CERTIFICATE_PEM: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBoDCCAUagAwIBAgIDAavNMAoGCCqGSM49BAMCMB4xHDAaBgNVBAMME2ZpeHR1\n\
+cmUuZXhhbXBsZS5jb20wHhcNMjYxMDE1MDQ1MzMwWhcNMzYxMDEyMDQ1MzMwWjAe\n\
+MRwwGgYDVQQDDBNmaXh0dXJlLmV4YW1wbGUuY29tMFkwEwYHKoZIzj0CAQYIKoZI\n\
+zj0DAQcDQgAEI9QUOYyE+DKoYrZzlMcv7QTtJh0cWePPS489Smg67iPd7kPIZ8oD\n\
+ZbqA6EJrbbcCwWkOIweDODHCoWSExLghlqNzMHEwHQYDVR0OBBYEFCjyDgLI6taO\n\
+Cj5IlQALL2NwtBVGMB8GA1UdIwQYMBaAFCjyDgLI6taOCj5IlQALL2NwtBVGMA8G\n\
+A1UdEwEB/wQFMAMBAf8wHgYDVR0RBBcwFYITZml4dHVyZS5leGFtcGxlLmNvbTAK\n\
+BggqhkjOPQQDAgNIADBFAiEAxXydnqQQNe7LyNlJjg0OVCfx7GyHT0BUb3STxAJI\n\
+VWUCIDZkPCfl0rjyE6iqBHVhjw2lyXUrwKrHY68h2XDx9em5\n\
+-----END CERTIFICATE-----\n";
+
+/// SHA-256 fingerprint of [`FIXTURE_CERTIFICATE_PEM`], as printed by
+/// `openssl x509 -noout -fingerprint -sha256`.
+pub const FIXTURE_CERTIFICATE_SHA256: &str = "\
+FD:CC:A8:13:2E:AD:F6:89:2D:F9:E8:43:59:8C:8F:DA:\
+45:5A:E8:0F:10:A5:70:08:52:64:07:E1:87:48:0A:88";
+
+fn get_terms() -> Response<impl MessageBody> {
+    Response::build(StatusCode::OK).body(TERMS)
+}
+
 fn head_new_nonce() -> Response<impl MessageBody> {
     Response::build(StatusCode::NO_CONTENT)
         .insert_header((
@@ -55,6 +97,15 @@ fn head_new_nonce() -> Response<impl MessageBody> {
         .finish()
 }
 
+fn get_new_nonce() -> Response<impl MessageBody> {
+    Response::build(StatusCode::OK)
+        .insert_header((
+            "Replay-Nonce",
+            "8_uBBV3N2DBRJczhoiB46ugJKUkUHxGzVe6xIMpjHFM",
+        ))
+        .finish()
+}
+
 fn post_new_acct(url: &str) -> Response<impl MessageBody> {
     const BODY: &str = r#"{
     "id": 7728515,
@@ -71,7 +122,8 @@ fn post_new_acct(url: &str) -> Response<impl MessageBody> {
     ],
     "initialIp": "90.171.37.12",
     "createdAt": "2018-12-31T17:15:40.399104457Z",
-    "status": "valid"
+    "status": "valid",
+    "orders": "<URL>/acme/acct/7728515/orders"
     }"#;
 
     let location = re_url()
@@ -80,9 +132,87 @@ fn post_new_acct(url: &str) -> Response<impl MessageBody> {
 
     Response::build(StatusCode::CREATED)
         .insert_header(("Location", location))
+        .body(re_url().replace_all(BODY, url).into_owned())
+}
+
+fn post_orders(url: &str, query: &str) -> Response<impl MessageBody> {
+    if query == "cursor=loop" {
+        let body = re_url().replace_all(
+            r#"{"orders": ["<URL>/acme/order/YTqpYUthlVfwBncUufE8"]}"#,
+            url,
+        );
+        let next = format!(r#"<{url}/acme/acct/7728515/orders?cursor=loop>;rel="next""#);
+
+        return Response::build(StatusCode::OK)
+            .insert_header(("Link", next))
+            .body(body.into_owned());
+    }
+
+    if query == "cursor=2" {
+        let body = re_url().replace_all(
+            r#"{"orders": ["<URL>/acme/order/YTqpYUthlVfwBncUufE8"]}"#,
+            url,
+        );
+        return Response::build(StatusCode::OK).body(body.into_owned());
+    }
+
+    let body = re_url().replace_all(
+        r#"{"orders": ["<URL>/acme/order/YTqpYUthlVfwBncUufE8"]}"#,
+        url,
+    );
+    let next = format!(r#"<{url}/acme/acct/7728515/orders?cursor=2>;rel="next""#);
+
+    Response::build(StatusCode::OK)
+        .insert_header(("Link", next))
+        .body(body.into_owned())
+}
+
+fn post_new_acct_missing() -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "type": "urn:ietf:params:acme:error:accountDoesNotExist",
+        "detail": "No account exists with the provided key"
+    }"#;
+
+    Response::build(StatusCode::BAD_REQUEST)
+        .insert_header(("content-type", "application/problem+json"))
         .body(BODY)
 }
 
+fn post_bad_nonce() -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "type": "urn:ietf:params:acme:error:badNonce",
+        "detail": "JWS has an invalid anti-replay nonce"
+    }"#;
+
+    Response::build(StatusCode::BAD_REQUEST)
+        .insert_header(("content-type", "application/problem+json"))
+        .body(BODY)
+}
+
+fn post_rate_limited() -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "type": "urn:ietf:params:acme:error:rateLimited",
+        "detail": "Too many new accounts"
+    }"#;
+
+    Response::build(StatusCode::TOO_MANY_REQUESTS)
+        .insert_header(("content-type", "application/problem+json"))
+        .insert_header(("Retry-After", "0"))
+        .body(BODY)
+}
+
+/// Whether `/acme/new-acct-rate-limited-once` has rejected a request yet.
+static RATE_LIMITED_SENT: AtomicBool = AtomicBool::new(false);
+
+/// Whether `/acme/new-acct-bad-nonce-once` has rejected a nonce yet.
+static BAD_NONCE_SENT: AtomicBool = AtomicBool::new(false);
+
+/// Number of requests to `/acme/new-acct-rate-limited-then-bad-nonce` so far.
+static RATE_LIMITED_THEN_BAD_NONCE_CALLS: AtomicU32 = AtomicU32::new(0);
+
+/// Number of requests to `/acme/order-processing/YTqpYUthlVfwBncUufE8` so far.
+static ORDER_PROCESSING_CALLS: AtomicU32 = AtomicU32::new(0);
+
 fn post_new_order(url: &str) -> Response<impl MessageBody> {
     const BODY: &str = r#"{
     "status": "pending",
@@ -108,7 +238,8 @@ fn post_new_order(url: &str) -> Response<impl MessageBody> {
         .body(re_url().replace_all(BODY, url))
 }
 
-fn post_get_order(url: &str) -> Response<impl MessageBody> {
+/// Returns the order in `status`; `<STATUS>` is left for the test rig to fill in.
+fn post_get_order(url: &str, status: &str) -> Response<impl MessageBody> {
     const BODY: &str = r#"{
     "status": "<STATUS>",
     "expires": "2019-01-09T08:26:43.570360537Z",
@@ -125,18 +256,61 @@ fn post_get_order(url: &str) -> Response<impl MessageBody> {
     "certificate": "<URL>/acme/cert/fae41c070f967713109028"
     }"#;
 
-    let body = re_url().replace_all(BODY, url).into_owned();
+    let body = re_url().replace_all(BODY, url).replace("<STATUS>", status);
 
     Response::build(StatusCode::OK).body(body)
 }
 
-fn post_authz(url: &str) -> Response<impl MessageBody> {
+/// Returns a pending order that expires in 2099, so that it can be resumed.
+fn post_get_order_unexpired(url: &str) -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+    "status": "pending",
+    "expires": "2099-01-09T08:26:43.570360537Z",
+    "identifiers": [
+        {
+        "type": "dns",
+        "value": "acme-test.example.com"
+        }
+    ],
+    "authorizations": [
+        "<URL>/acme/authz/YTqpYUthlVfwBncUufE8IRWLMSRqcSs"
+    ],
+    "finalize": "<URL>/acme/finalize/7738992/18234324"
+    }"#;
+
+    Response::build(StatusCode::OK).body(re_url().replace_all(BODY, url).into_owned())
+}
+
+fn post_get_order_invalid(url: &str) -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+    "status": "invalid",
+    "expires": "2019-01-09T08:26:43.570360537Z",
+    "identifiers": [
+        {
+        "type": "dns",
+        "value": "acme-test.example.com"
+        }
+    ],
+    "authorizations": [
+        "<URL>/acme/authz/YTqpYUthlVfwBncUufE8IRWLMSRqcSs"
+    ],
+    "finalize": "<URL>/acme/finalize/7738992/18234324",
+    "error": {
+        "type": "urn:ietf:params:acme:error:badCSR",
+        "detail": "Issuance refused"
+    }
+    }"#;
+
+    Response::build(StatusCode::OK).body(re_url().replace_all(BODY, url).into_owned())
+}
+
+fn post_authz(url: &str, body: &[u8]) -> Response<impl MessageBody> {
     const BODY: &str = r#"{
         "identifier": {
             "type": "dns",
             "value": "acmetest.algesten.se"
         },
-        "status": "pending",
+        "status": "<STATUS>",
         "expires": "2019-01-09T08:26:43Z",
         "challenges": [
         {
@@ -160,30 +334,349 @@ fn post_authz(url: &str) -> Response<impl MessageBody> {
         ]
     }"#;
 
-    Response::build(StatusCode::CREATED).body(re_url().replace_all(BODY, url))
+    // deactivation requests carry a `status` in their payload; POST-as-GET requests do not
+    let status = jws_payload(body)
+        .get("status")
+        .and_then(|status| status.as_str())
+        .unwrap_or("pending")
+        .to_owned();
+
+    let body = re_url().replace_all(BODY, url).replace("<STATUS>", &status);
+
+    Response::build(StatusCode::CREATED).body(body)
+}
+
+fn post_authz_valid(url: &str) -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "identifier": {
+            "type": "dns",
+            "value": "acmetest.algesten.se"
+        },
+        "status": "valid",
+        "expires": "2019-01-09T08:26:43Z",
+        "challenges": [
+        {
+            "type": "http-01",
+            "status": "valid",
+            "url": "<URL>/acme/challenge/YTqpYUthlVfwBncUufE8IRWLMSRqcSs/216789597",
+            "token": "MUi-gqeOJdRkSb_YR2eaMxQBqf6al8dgt_dOttSWb0w",
+            "validated": "2019-01-02T08:26:43Z"
+        }
+        ]
+    }"#;
+
+    Response::build(StatusCode::OK).body(re_url().replace_all(BODY, url))
+}
+
+fn post_authz_invalid(url: &str) -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "identifier": {
+            "type": "dns",
+            "value": "acmetest.algesten.se"
+        },
+        "status": "invalid",
+        "expires": "2019-01-09T08:26:43Z",
+        "challenges": [
+        {
+            "type": "http-01",
+            "status": "invalid",
+            "url": "<URL>/acme/challenge/YTqpYUthlVfwBncUufE8IRWLMSRqcSs/216789597",
+            "token": "MUi-gqeOJdRkSb_YR2eaMxQBqf6al8dgt_dOttSWb0w",
+            "error": {
+                "type": "urn:ietf:params:acme:error:compound",
+                "detail": "Errors for multiple identifiers",
+                "status": 403,
+                "subproblems": [
+                {
+                    "type": "urn:ietf:params:acme:error:caa",
+                    "detail": "CAA record forbids issuance",
+                    "identifier": {
+                        "type": "dns",
+                        "value": "acmetest.algesten.se"
+                    }
+                },
+                {
+                    "type": "urn:ietf:params:acme:error:dns",
+                    "detail": "No valid IP addresses found",
+                    "identifier": {
+                        "type": "dns",
+                        "value": "www.acmetest.algesten.se"
+                    }
+                }
+                ]
+            }
+        }
+        ]
+    }"#;
+
+    Response::build(StatusCode::OK).body(re_url().replace_all(BODY, url))
+}
+
+fn post_challenge(url: &str, path: &str) -> Response<impl MessageBody> {
+    const BODY: &str = r#"{
+        "type": "http-01",
+        "status": "pending",
+        "url": "<URL><PATH>",
+        "token": "MUi-gqeOJdRkSb_YR2eaMxQBqf6al8dgt_dOttSWb0w"
+    }"#;
+
+    let body = re_url().replace_all(BODY, url).replace("<PATH>", path);
+    let up = format!(r#"<{url}/acme/authz/YTqpYUthlVfwBncUufE8IRWLMSRqcSs>;rel="up""#);
+
+    Response::build(StatusCode::OK)
+        .insert_header(("Link", up))
+        .body(body)
 }
 
 fn post_finalize(_url: &str) -> Response<impl MessageBody> {
     Response::ok()
 }
 
-fn post_certificate(_url: &str) -> Response<impl MessageBody> {
-    Response::build(StatusCode::OK).body("CERT HERE")
+fn post_certificate(url: &str) -> Response<impl MessageBody> {
+    let alternate = format!(r#"<{url}/acme/cert/fae41c070f967713109028/1>;rel="alternate""#);
+
+    Response::build(StatusCode::OK)
+        .insert_header(("Link", alternate))
+        .body("CERT HERE")
+}
+
+fn post_alternate_certificate() -> Response<impl MessageBody> {
+    let cert = self_signed_certificate_with_cn(ALTERNATE_ROOT);
+    Response::build(StatusCode::OK).body(cert.certificate().to_owned())
+}
+
+fn post_malformed(detail: &str) -> Response<impl MessageBody> {
+    let body = serde_json::json!({
+        "type": "urn:ietf:params:acme:error:malformed",
+        "detail": detail,
+    });
+
+    Response::build(StatusCode::BAD_REQUEST)
+        .insert_header(("content-type", "application/problem+json"))
+        .body(body.to_string())
+}
+
+fn post_key_change(url: &str, body: &[u8]) -> Response<impl MessageBody> {
+    let account = format!("{url}/acme/acct/7728515");
+
+    let outer = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+    let (outer_protected, inner) = jws_parts(&outer);
+    let (inner_protected, payload) = jws_parts(&inner);
+
+    // see RFC 8555 §7.3.5
+    let valid = outer_protected["kid"] == account.as_str()
+        && inner_protected.get("jwk").is_some()
+        && inner_protected.get("kid").is_none()
+        && inner_protected.get("nonce").is_none()
+        && inner_protected["url"] == outer_protected["url"]
+        && payload["account"] == account.as_str()
+        && jws_verified_by(&inner, &inner_protected["jwk"])
+        && jws_verified_by(&outer, &payload["oldKey"]);
+
+    if valid {
+        Response::build(StatusCode::OK)
+            .finish()
+            .map_into_boxed_body()
+    } else {
+        post_malformed("Invalid key change request").map_into_boxed_body()
+    }
 }
 
-fn route_request(req: Request, url: &str) -> Response<impl MessageBody> {
+fn post_revoke_cert(url: &str, body: &[u8], with_key: bool) -> Response<impl MessageBody> {
+    use base64::prelude::*;
+
+    let cert = revocable_certificate();
+    let cert_der = &cert.certificate_chain().unwrap()[0];
+
+    let jws = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+    let (protected, payload) = jws_parts(&jws);
+
+    let signed_as_expected = if with_key {
+        // signed using the certificate's own key, see RFC 8555 §7.6
+        let cert_key = crate::acc::AcmeKey::from_key(cert.signing_key().unwrap());
+
+        protected.get("kid").is_none()
+            && crate::jws::jwk_value_thumbprint(&protected["jwk"]).ok()
+                == crate::jws::jwk_thumbprint(&cert_key).ok()
+            && jws_verified_by(&jws, &protected["jwk"])
+    } else {
+        protected.get("jwk").is_none()
+            && protected["kid"] == format!("{url}/acme/acct/7728515").as_str()
+    };
+
+    let valid = signed_as_expected
+        && payload["certificate"] == BASE64_URL_SAFE_NO_PAD.encode(cert_der).as_str()
+        && payload["reason"].is_u64();
+
+    if valid {
+        Response::build(StatusCode::OK)
+            .finish()
+            .map_into_boxed_body()
+    } else {
+        post_malformed("Invalid revocation request").map_into_boxed_body()
+    }
+}
+
+/// Returns the decoded protected header and payload of the flattened JWS `jws`.
+///
+/// The payload is `null` for POST-as-GET requests.
+fn jws_parts(jws: &serde_json::Value) -> (serde_json::Value, serde_json::Value) {
+    use base64::prelude::*;
+
+    let decode = |part: &serde_json::Value| {
+        let json = BASE64_URL_SAFE_NO_PAD
+            .decode(part.as_str().unwrap())
+            .unwrap();
+
+        if json.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&json).unwrap()
+        }
+    };
+
+    (decode(&jws["protected"]), decode(&jws["payload"]))
+}
+
+/// Returns the decoded payload of the flattened JWS `body`.
+fn jws_payload(body: &[u8]) -> serde_json::Value {
+    let jws = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+    jws_parts(&jws).1
+}
+
+/// Returns true if the flattened JWS `jws` is signed by the P-256 key `jwk`.
+fn jws_verified_by(jws: &serde_json::Value, jwk: &serde_json::Value) -> bool {
+    use base64::prelude::*;
+    use p256::ecdsa::{signature::Verifier as _, Signature, VerifyingKey};
+
+    // the JWK parser rejects the optional `alg` and `use` members
+    let jwk = serde_json::json!({
+        "kty": jwk["kty"],
+        "crv": jwk["crv"],
+        "x": jwk["x"],
+        "y": jwk["y"],
+    });
+
+    let Ok(key) = p256::PublicKey::from_jwk_str(&jwk.to_string()) else {
+        return false;
+    };
+
+    let Some(signature) = jws["signature"]
+        .as_str()
+        .and_then(|sig| BASE64_URL_SAFE_NO_PAD.decode(sig).ok())
+        .and_then(|sig| Signature::from_slice(&sig).ok())
+    else {
+        return false;
+    };
+
+    let signing_input = format!(
+        "{}.{}",
+        jws["protected"].as_str().unwrap_or_default(),
+        jws["payload"].as_str().unwrap_or_default(),
+    );
+
+    VerifyingKey::from(key)
+        .verify(signing_input.as_bytes(), &signature)
+        .is_ok()
+}
+
+fn route_request(req: Request, body: &[u8], url: &str) -> Response<impl MessageBody> {
     match (req.method(), req.path()) {
         (&Method::GET, "/directory") => get_directory(url).map_into_boxed_body(),
+        (&Method::GET, "/terms") => get_terms().map_into_boxed_body(),
         (&Method::HEAD, "/acme/new-nonce") => head_new_nonce().map_into_boxed_body(),
-        (&Method::POST, "/acme/new-acct") => post_new_acct(url).map_into_boxed_body(),
-        (&Method::POST, "/acme/new-order") => post_new_order(url).map_into_boxed_body(),
+        (&Method::GET, "/acme/new-nonce-get") => get_new_nonce().map_into_boxed_body(),
+        (_, "/acme/new-nonce-missing") => Response::build(StatusCode::NO_CONTENT)
+            .finish()
+            .map_into_boxed_body(),
+        (&Method::POST, "/acme/new-acct" | "/acme/acct/7728515") => {
+            post_new_acct(url).map_into_boxed_body()
+        }
+        (&Method::POST, "/acme/new-acct-missing") => post_new_acct_missing().map_into_boxed_body(),
+        (&Method::POST, "/acme/new-acct-bad-nonce") => post_bad_nonce().map_into_boxed_body(),
+        (&Method::POST, "/acme/new-acct-rate-limited") => post_rate_limited().map_into_boxed_body(),
+        (&Method::POST, "/acme/new-acct-rate-limited-once") => {
+            if RATE_LIMITED_SENT.swap(true, Ordering::SeqCst) {
+                post_new_acct(url).map_into_boxed_body()
+            } else {
+                post_rate_limited().map_into_boxed_body()
+            }
+        }
+        (&Method::POST, "/acme/new-acct-bad-nonce-once") => {
+            if BAD_NONCE_SENT.swap(true, Ordering::SeqCst) {
+                post_new_acct(url).map_into_boxed_body()
+            } else {
+                post_bad_nonce().map_into_boxed_body()
+            }
+        }
+        (&Method::POST, "/acme/new-acct-rate-limited-then-bad-nonce") => {
+            // rate limited three times, then one bad nonce
+            match RATE_LIMITED_THEN_BAD_NONCE_CALLS.fetch_add(1, Ordering::SeqCst) {
+                0..=2 => post_rate_limited().map_into_boxed_body(),
+                3 => post_bad_nonce().map_into_boxed_body(),
+                _ => post_new_acct(url).map_into_boxed_body(),
+            }
+        }
+        (&Method::POST, "/acme/new-order") => match req.headers().get("idempotency-key") {
+            Some(_) => post_malformed("Unexpected Idempotency-Key header").map_into_boxed_body(),
+            None => post_new_order(url).map_into_boxed_body(),
+        },
+        (&Method::POST, "/acme/new-order-idempotent") => {
+            match req.headers().get("idempotency-key") {
+                Some(key) if key == IDEMPOTENCY_KEY => post_new_order(url).map_into_boxed_body(),
+                _ => post_malformed("Missing Idempotency-Key header").map_into_boxed_body(),
+            }
+        }
+        (&Method::POST, "/acme/acct/7728515/orders") => {
+            post_orders(url, req.uri().query().unwrap_or_default()).map_into_boxed_body()
+        }
 
         (&Method::POST, "/acme/order/YTqpYUthlVfwBncUufE8") => {
-            post_get_order(url).map_into_boxed_body()
+            post_get_order(url, "<STATUS>").map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/order-processing/YTqpYUthlVfwBncUufE8") => {
+            // ready when first polled, then stuck processing after finalization
+            match ORDER_PROCESSING_CALLS.fetch_add(1, Ordering::SeqCst) {
+                0 => post_get_order(url, "ready").map_into_boxed_body(),
+                _ => post_get_order(url, "processing").map_into_boxed_body(),
+            }
+        }
+
+        (&Method::POST, "/acme/order-stuck/YTqpYUthlVfwBncUufE8") => {
+            post_get_order(url, "processing").map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/order-ready/YTqpYUthlVfwBncUufE8") => {
+            post_get_order(url, "ready").map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/order-unexpired/YTqpYUthlVfwBncUufE8") => {
+            post_get_order_unexpired(url).map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/order-invalid/YTqpYUthlVfwBncUufE8") => {
+            post_get_order_invalid(url).map_into_boxed_body()
         }
 
         (&Method::POST, "/acme/authz/YTqpYUthlVfwBncUufE8IRWLMSRqcSs") => {
-            post_authz(url).map_into_boxed_body()
+            post_authz(url, body).map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/authz-valid/YTqpYUthlVfwBncUufE8IRWLMSRqcSs") => {
+            post_authz_valid(url).map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/authz-invalid/YTqpYUthlVfwBncUufE8IRWLMSRqcSs") => {
+            post_authz_invalid(url).map_into_boxed_body()
+        }
+
+        (&Method::POST, path) if path.starts_with("/acme/challenge/") => {
+            post_challenge(url, path).map_into_boxed_body()
+        }
+
+        (&Method::POST, path) if path.starts_with("/acme/challenge-triggered/") => {
+            post_malformed("Challenge was already triggered").map_into_boxed_body()
         }
 
         (&Method::POST, "/acme/finalize/7738992/18234324") => {
@@ -194,12 +687,91 @@ fn route_request(req: Request, url: &str) -> Response<impl MessageBody> {
             post_certificate(url).map_into_boxed_body()
         }
 
+        (&Method::POST, "/acme/key-change") => post_key_change(url, body).map_into_boxed_body(),
+
+        (&Method::POST, "/acme/revoke-cert") => {
+            post_revoke_cert(url, body, false).map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/revoke-cert-with-key") => {
+            post_revoke_cert(url, body, true).map_into_boxed_body()
+        }
+
+        (&Method::POST, "/acme/cert/fae41c070f967713109028/1") => {
+            post_alternate_certificate().map_into_boxed_body()
+        }
+
         (_, _) => Response::build(StatusCode::NOT_FOUND)
             .finish()
             .map_into_boxed_body(),
     }
 }
 
+static REVOCABLE_CERTIFICATE: OnceLock<crate::Certificate> = OnceLock::new();
+
+/// Returns the only certificate that the mock ACME API accepts revocation requests for.
+pub fn revocable_certificate() -> crate::Certificate {
+    REVOCABLE_CERTIFICATE
+        .get_or_init(|| self_signed_certificate("acme-test.example.com"))
+        .clone()
+}
+
+/// Returns a certificate for `domain`, self-signed with a new P-256 key.
+pub fn self_signed_certificate(domain: &str) -> crate::Certificate {
+    let params = rcgen::CertificateParams::new(vec![domain.to_owned()]).unwrap();
+    self_signed_certificate_from_params(params)
+}
+
+/// Returns a certificate with subject (and issuer) common name `cn`, self-signed with a new P-256
+/// key.
+pub fn self_signed_certificate_with_cn(cn: &str) -> crate::Certificate {
+    let mut params = rcgen::CertificateParams::default();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, cn);
+    self_signed_certificate_from_params(params)
+}
+
+fn self_signed_certificate_from_params(params: rcgen::CertificateParams) -> crate::Certificate {
+    use pkcs8::EncodePrivateKey as _;
+
+    let key_pem = crate::create_p256_key()
+        .to_pkcs8_pem(pkcs8::LineEnding::LF)
+        .unwrap();
+    let key_pair = rcgen::KeyPair::from_pem(&key_pem).unwrap();
+    let cert = params.self_signed(&key_pair).unwrap();
+
+    crate::Certificate::parse(key_pem, cert.pem()).unwrap()
+}
+
+/// Starts a plain HTTP server responding to every request with `status` (e.g., `"200 OK"`) and
+/// `body`, and returns its base URL.
+pub fn with_static_http_server(status: &'static str, body: String) -> String {
+    use std::io::{BufRead as _, BufReader, Write as _};
+
+    let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", lst.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for mut stream in lst.incoming().flatten() {
+            // skip the request line and headers
+            let mut rdr = BufReader::new(&stream);
+            let mut line = String::new();
+            while rdr.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+
+            let res = format!(
+                "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len(),
+            );
+            let _ = stream.write_all(res.as_bytes());
+        }
+    });
+
+    url
+}
+
 pub fn with_directory_server() -> TestServer {
     let lst = TcpListener::bind("127.0.0.1:0").unwrap();
     let port = lst.local_addr().unwrap().port();
@@ -212,7 +784,20 @@ pub fn with_directory_server() -> TestServer {
             let url = url.clone();
 
             HttpService::build()
-                .finish(move |req| ready(Ok::<_, Infallible>(route_request(req, &url))))
+                .finish(move |mut req: Request| {
+                    let url = url.clone();
+
+                    async move {
+                        let mut payload = req.take_payload();
+                        let mut body = Vec::new();
+
+                        while let Some(chunk) = payload.next().await {
+                            body.extend_from_slice(&chunk.unwrap());
+                        }
+
+                        Ok::<_, Infallible>(route_request(req, &body, &url))
+                    }
+                })
                 .tcp()
         })
         .unwrap()
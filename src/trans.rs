@@ -1,15 +1,20 @@
-use std::{collections::VecDeque, sync::Arc};
+use std::{collections::VecDeque, fmt, sync::Arc};
 
 use base64::prelude::*;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::Serialize;
 
 use crate::{
     acc::AcmeKey,
-    jws::{FlattenedJsonJws, Jwk, JwsProtectedHeader},
-    req::{req_expect_header, req_handle_error, req_head, req_post},
+    http::HttpClient,
+    jws::{FlattenedJsonJws, Jwk, JwsFormat, JwsProtectedHeader},
+    req::{req_expect_header, req_get, req_handle_error, req_head, req_post, req_retry_after},
+    Error, RateLimitRetry,
 };
 
+/// Default number of times each call is retried on bad nonces.
+pub(crate) const DEFAULT_MAX_BAD_NONCE_RETRIES: u32 = 3;
+
 /// JWS payload and nonce handling for requests to the API.
 ///
 /// Setup is:
@@ -20,26 +25,72 @@ use crate::{
 /// 4. `call()` for all calls after that.
 #[derive(Debug, Clone)]
 pub(crate) struct Transport {
-    acme_key: AcmeKey,
+    /// Shared between clones so that a key rollover applies to all of an account's orders.
+    acme_key: Arc<RwLock<AcmeKey>>,
     nonce_pool: Arc<NoncePool>,
+    max_bad_nonce_retries: u32,
+    rate_limit_retry: Option<RateLimitRetry>,
+    rewrite_url: Option<RewriteUrl>,
+    client: HttpClient,
 }
 
 impl Transport {
-    pub fn new(nonce_pool: Arc<NoncePool>, acme_key: AcmeKey) -> Self {
+    pub fn new(nonce_pool: Arc<NoncePool>, acme_key: AcmeKey, client: HttpClient) -> Self {
         Transport {
-            acme_key,
+            acme_key: Arc::new(RwLock::new(acme_key)),
             nonce_pool,
+            max_bad_nonce_retries: DEFAULT_MAX_BAD_NONCE_RETRIES,
+            rate_limit_retry: None,
+            rewrite_url: None,
+            client,
         }
     }
 
+    /// Rewrite URLs before making calls to them.
+    pub fn set_rewrite_url(&mut self, rewrite_url: Option<RewriteUrl>) {
+        self.rewrite_url = rewrite_url;
+    }
+
+    /// Limit the number of times each call is retried on bad nonces.
+    pub fn set_max_bad_nonce_retries(&mut self, max_bad_nonce_retries: u32) {
+        self.max_bad_nonce_retries = max_bad_nonce_retries;
+    }
+
+    /// Retry calls rejected by rate limits, if configured.
+    pub fn set_rate_limit_retry(&mut self, rate_limit_retry: Option<RateLimitRetry>) {
+        self.rate_limit_retry = rate_limit_retry;
+    }
+
     /// Update the key ID once it is known (part of setting up the transport).
     pub fn set_key_id(&mut self, kid: String) {
-        self.acme_key.set_key_id(kid);
+        self.acme_key.write().set_key_id(kid);
     }
 
     /// The key used in the transport
-    pub fn acme_key(&self) -> &AcmeKey {
-        &self.acme_key
+    pub fn acme_key(&self) -> AcmeKey {
+        self.acme_key.read().clone()
+    }
+
+    /// The HTTP client used by the transport.
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// Roll over to `new_key` using the keyChange endpoint at `url`.
+    ///
+    /// The key ID is preserved. See [RFC 8555 §7.3.5].
+    ///
+    /// [RFC 8555 §7.3.5]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5
+    pub async fn change_key(&self, url: &str, mut new_key: AcmeKey) -> eyre::Result<()> {
+        let old_key = self.acme_key();
+        let key_change = jws_key_change(url, &old_key, &new_key)?;
+
+        self.call_kid(url, &key_change).await?;
+
+        new_key.set_key_id(old_key.key_id().to_owned());
+        *self.acme_key.write() = new_key;
+
+        Ok(())
     }
 
     /// Make call using the full JWS.
@@ -49,18 +100,10 @@ impl Transport {
     where
         T: Serialize + ?Sized,
     {
-        fn jws_with_jwk<T: Serialize + ?Sized>(
-            url: &str,
-            nonce: String,
-            key: &AcmeKey,
-            payload: &T,
-        ) -> eyre::Result<String> {
-            let jwk = Jwk::try_from(key)?;
-            let protected = JwsProtectedHeader::new_jwk(jwk, url, nonce);
-            jws_with(protected, key, payload)
-        }
-
-        self.do_call(url, body, jws_with_jwk).await
+        self.do_call(url, body, &[], |url, nonce, key, payload| {
+            jws_with_jwk(url, nonce, key, payload, JwsFormat::Flattened)
+        })
+        .await
     }
 
     /// Make call using the key ID.
@@ -68,23 +111,58 @@ impl Transport {
     where
         T: Serialize + ?Sized,
     {
-        fn jws_with_kid<T: Serialize + ?Sized>(
-            url: &str,
-            nonce: String,
-            key: &AcmeKey,
-            payload: &T,
-        ) -> eyre::Result<String> {
-            let protected = JwsProtectedHeader::new_kid(key.key_id(), url, nonce);
-            jws_with(protected, key, payload)
-        }
+        self.call_kid_with_headers(url, body, &[]).await
+    }
 
-        self.do_call(url, body, jws_with_kid).await
+    /// Make call using the key ID, sending additional HTTP `headers`.
+    ///
+    /// The headers are sent again if the call is retried.
+    pub async fn call_kid_with_headers<T>(
+        &self,
+        url: &str,
+        body: &T,
+        headers: &[(&str, &str)],
+    ) -> eyre::Result<reqwest::Response>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.do_call(url, body, headers, |url, nonce, key, payload| {
+            jws_with_kid(url, nonce, key, payload, JwsFormat::Flattened)
+        })
+        .await
     }
 
+    /// Sign `body` using the full JWS, without making a call.
+    ///
+    /// A nonce is taken from the pool, as if a call was made.
+    pub async fn sign_jwk<T>(&self, url: &str, body: &T, format: JwsFormat) -> eyre::Result<String>
+    where
+        T: Serialize + ?Sized,
+    {
+        let nonce = self.nonce_pool.get_nonce().await?;
+        jws_with_jwk(url, nonce, &self.acme_key.read(), body, format)
+    }
+
+    /// Sign `body` using the key ID, without making a call.
+    ///
+    /// A nonce is taken from the pool, as if a call was made.
+    pub async fn sign_kid<T>(&self, url: &str, body: &T, format: JwsFormat) -> eyre::Result<String>
+    where
+        T: Serialize + ?Sized,
+    {
+        let nonce = self.nonce_pool.get_nonce().await?;
+        jws_with_kid(url, nonce, &self.acme_key.read(), body, format)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(url = %url))
+    )]
     async fn do_call<T, F>(
         &self,
         url: &str,
         body: &T,
+        headers: &[(&str, &str)],
         make_body: F,
     ) -> eyre::Result<reqwest::Response>
     where
@@ -93,68 +171,149 @@ impl Transport {
     {
         // The ACME API may at any point invalidate all nonces. If we detect such an
         // error, we loop until the server accepts the nonce.
+        let mut bad_nonce_retries = 0;
+        let mut rate_limit_retries = 0;
+
         loop {
             // Either get a new nonce, or reuse one from a previous request.
             let nonce = self.nonce_pool.get_nonce().await?;
 
+            // The signed body is not recorded since it contains the JWS signature.
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                attempt = bad_nonce_retries + rate_limit_retries + 1,
+                %nonce,
+                "Calling endpoint"
+            );
+
             // Sign the body.
-            let body = make_body(url, nonce, &self.acme_key, body)?;
+            let body = make_body(url, nonce, &self.acme_key.read(), body)?;
 
-            log::debug!("Call endpoint: {url}");
+            // The JWS is signed for the URL returned by the ACME API but sent to the rewritten URL.
+            let post_url = match &self.rewrite_url {
+                Some(rewrite_url) => rewrite_url.apply(url),
+                None => url.to_owned(),
+            };
+
+            log::debug!("Call endpoint: {post_url}");
 
             // Post it to the URL
-            let response = req_post(url, &body).await;
+            let response = req_post(&self.client, &post_url, &body, headers).await?;
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(status = %response.status(), "Received response");
 
             // Regardless of the request being a success or not, there might be a nonce in the
             // response.
             self.nonce_pool.extract_nonce(&response);
 
+            let retry_after = req_retry_after(&response);
+
             // Turn errors into ApiProblem.
             let result = req_handle_error(response).await;
 
+            #[cfg(feature = "tracing")]
             if let Err(problem) = &result {
+                tracing::debug!(problem_type = %problem._type, detail = ?problem.detail, "Received problem");
+            }
+
+            if let (Some(rate_limit_retry), Err(problem)) = (&self.rate_limit_retry, &result) {
+                if problem.is_rate_limited() && rate_limit_retries < rate_limit_retry.max_retries()
+                {
+                    rate_limit_retries += 1;
+
+                    let delay = rate_limit_retry.delay(rate_limit_retries, retry_after);
+
+                    log::debug!("Retrying in {delay:?} on: {problem}");
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            }
+
+            // Rate limit retries do not count towards the bad nonce retries.
+            let can_retry = bad_nonce_retries < self.max_bad_nonce_retries;
+
+            if let (true, Err(problem)) = (can_retry, &result) {
                 if problem.is_bad_nonce() {
                     // retry the request with a new nonce.
+                    bad_nonce_retries += 1;
                     log::debug!("Retrying on bad nonce");
                     continue;
                 }
 
                 // it seems we sometimes make bad JWTs. Why?!
                 if problem.is_jws_verification_error() {
+                    bad_nonce_retries += 1;
                     log::debug!("Retrying on: {problem}");
                     continue;
                 }
             }
 
-            return Ok(result?);
+            return result.map_err(|problem| {
+                Error::Problem {
+                    url: url.to_owned(),
+                    problem,
+                }
+                .into()
+            });
         }
     }
 }
 
+/// Function rewriting URLs returned by the ACME API before they are requested.
+///
+/// See [`DirectoryBuilder::rewrite_url()`](crate::DirectoryBuilder::rewrite_url()).
+#[derive(Clone)]
+pub(crate) struct RewriteUrl(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl RewriteUrl {
+    pub fn new(rewrite_url: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        RewriteUrl(Arc::new(rewrite_url))
+    }
+
+    pub fn apply(&self, url: &str) -> String {
+        (self.0)(url)
+    }
+}
+
+impl fmt::Debug for RewriteUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RewriteUrl(..)")
+    }
+}
+
 /// Shared pool of nonces.
-#[derive(Debug, Default)]
+///
+/// Nonces from the `Replay-Nonce` header of every response are stored for use in subsequent
+/// requests, so the newNonce endpoint is only requested when the pool is empty. Each nonce is
+/// removed from the pool when taken, so concurrent requests never use the same nonce.
+#[derive(Debug)]
 pub(crate) struct NoncePool {
     nonce_url: String,
     pool: Mutex<VecDeque<String>>,
+    client: HttpClient,
 }
 
 impl NoncePool {
-    pub fn new(nonce_url: &str) -> Self {
+    pub fn new(nonce_url: &str, client: HttpClient) -> Self {
         NoncePool {
             nonce_url: nonce_url.to_owned(),
-            ..Default::default()
+            pool: Mutex::default(),
+            client,
         }
     }
 
     fn extract_nonce(&self, res: &reqwest::Response) {
         if let Some(nonce) = res.headers().get("replay-nonce") {
+            let Some(nonce) = nonce.to_str().ok().filter(|nonce| is_valid_nonce(nonce)) else {
+                log::debug!("Ignoring invalid nonce");
+                return;
+            };
+
             log::trace!("Extracting new nonce");
 
             let mut pool = self.pool.lock();
-
-            // TODO: ignore invalid replay-nonce values
-            // see https://datatracker.ietf.org/doc/html/rfc8555#section-6.5.1
-            pool.push_back(nonce.to_str().unwrap().to_owned());
+            pool.push_back(nonce.to_owned());
 
             if pool.len() > 10 {
                 pool.pop_front();
@@ -173,14 +332,102 @@ impl NoncePool {
         }
 
         log::debug!("Request new nonce");
-        let res = req_head(&self.nonce_url).await;
+        let res = req_head(&self.client, &self.nonce_url).await?;
+
+        if let Some(nonce) = nonce_from_response(&res) {
+            return Ok(nonce);
+        }
+
+        // some servers only support GET requests to newNonce
+        log::debug!("No nonce in HEAD response; retrying with GET");
+        let res = req_get(&self.client, &self.nonce_url).await?;
 
-        // TODO: ignore invalid replay-nonce values
-        // see https://datatracker.ietf.org/doc/html/rfc8555#section-6.5.1
-        Ok(req_expect_header(&res, "replay-nonce")?)
+        if let Some(nonce) = nonce_from_response(&res) {
+            return Ok(nonce);
+        }
+
+        // a successful response without a nonce indicates a misconfigured newNonce endpoint
+        if res.status().is_success() {
+            return Err(Error::MissingNonceHeader {
+                url: self.nonce_url.clone(),
+                status: res.status().as_u16(),
+            }
+            .into());
+        }
+
+        Err(eyre::eyre!(
+            "No Replay-Nonce header in response to HEAD or GET {} (last status: {})",
+            self.nonce_url,
+            res.status(),
+        ))
     }
 }
 
+/// Extracts the nonce from a successful (i.e., 200 or 204) newNonce response.
+fn nonce_from_response(res: &reqwest::Response) -> Option<String> {
+    if !res.status().is_success() {
+        return None;
+    }
+
+    req_expect_header(res, "replay-nonce")
+        .ok()
+        .filter(|nonce| is_valid_nonce(nonce))
+}
+
+/// Returns true if `nonce` is a syntactically valid `Replay-Nonce` header value, i.e., a non-empty
+/// base64url string without padding.
+///
+/// See [RFC 8555 §6.5.1].
+///
+/// [RFC 8555 §6.5.1]: https://datatracker.ietf.org/doc/html/rfc8555#section-6.5.1
+fn is_valid_nonce(nonce: &str) -> bool {
+    crate::util::is_base64url(nonce)
+}
+
+fn jws_with_jwk<T: Serialize + ?Sized>(
+    url: &str,
+    nonce: String,
+    key: &AcmeKey,
+    payload: &T,
+    format: JwsFormat,
+) -> eyre::Result<String> {
+    let jwk = Jwk::try_from(key)?;
+    let protected = JwsProtectedHeader::new_jwk(jwk, url, nonce);
+    jws_with(protected, key, payload, format)
+}
+
+fn jws_with_kid<T: Serialize + ?Sized>(
+    url: &str,
+    nonce: String,
+    key: &AcmeKey,
+    payload: &T,
+    format: JwsFormat,
+) -> eyre::Result<String> {
+    let protected = JwsProtectedHeader::new_kid(key.key_id(), key.alg(), url, nonce);
+    jws_with(protected, key, payload, format)
+}
+
+/// Construct the inner JWS of a keyChange request, signed by `new_key`.
+///
+/// See [RFC 8555 §7.3.5].
+///
+/// [RFC 8555 §7.3.5]: https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5
+fn jws_key_change(
+    url: &str,
+    old_key: &AcmeKey,
+    new_key: &AcmeKey,
+) -> eyre::Result<serde_json::Value> {
+    let payload = serde_json::json!({
+        "account": old_key.key_id(),
+        "oldKey": Jwk::try_from(old_key)?,
+    });
+
+    let protected = JwsProtectedHeader::new_key_change(Jwk::try_from(new_key)?, url);
+    let jws = jws_with(protected, new_key, &payload, JwsFormat::Flattened)?;
+
+    Ok(serde_json::from_str(&jws)?)
+}
+
 /// Construct JWS with protected header according to [RFC 7515 §5.1].
 ///
 /// [RFC 7515 §5.1]: https://datatracker.ietf.org/doc/html/rfc7515#section-5.1
@@ -188,6 +435,7 @@ fn jws_with<T: Serialize + ?Sized>(
     protected: JwsProtectedHeader,
     key: &AcmeKey,
     payload: &T,
+    format: JwsFormat,
 ) -> eyre::Result<String> {
     let header = {
         let pro_json = serde_json::to_string(&protected)?;
@@ -208,14 +456,125 @@ fn jws_with<T: Serialize + ?Sized>(
     };
 
     let to_sign = format!("{header}.{payload}");
-    let (signature, _rec_id) = key
-        .private_key()
-        .sign_recoverable(to_sign.as_bytes())
+    let signature = BASE64_URL_SAFE_NO_PAD.encode(key.sign(to_sign.as_bytes()));
+
+    match format {
+        JwsFormat::Flattened => {
+            let jws = FlattenedJsonJws::new(header, payload, signature);
+            Ok(serde_json::to_string(&jws)?)
+        }
+
+        JwsFormat::Compact => Ok(format!("{header}.{payload}.{signature}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jws_formats() {
+        let mut key = AcmeKey::new();
+        key.set_key_id("https://example.com/acme/acct/1".to_owned());
+
+        let url = "https://example.com/acme/new-order";
+        let payload = serde_json::json!({ "foo": "bar" });
+
+        let flattened = jws_with_kid(
+            url,
+            "nonce".to_owned(),
+            &key,
+            &payload,
+            JwsFormat::Flattened,
+        )
         .unwrap();
+        let flattened = serde_json::from_str::<serde_json::Value>(&flattened).unwrap();
+
+        let compact =
+            jws_with_kid(url, "nonce".to_owned(), &key, &payload, JwsFormat::Compact).unwrap();
+        let parts = compact.split('.').collect::<Vec<_>>();
+
+        // signatures are deterministic (RFC 6979) so both formats carry the same signature
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], flattened["protected"]);
+        assert_eq!(parts[1], flattened["payload"]);
+        assert_eq!(parts[2], flattened["signature"]);
+    }
+
+    #[test]
+    fn test_jws_key_change() {
+        let mut old_key = AcmeKey::new();
+        old_key.set_key_id("https://example.com/acme/acct/1".to_owned());
+        let new_key = AcmeKey::new();
+
+        let url = "https://example.com/acme/key-change";
+        let jws = jws_key_change(url, &old_key, &new_key).unwrap();
+
+        let decode = |part: &serde_json::Value| {
+            let json = BASE64_URL_SAFE_NO_PAD
+                .decode(part.as_str().unwrap())
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap()
+        };
+
+        let protected = decode(&jws["protected"]);
+        assert_eq!(protected["url"], url);
+        assert_eq!(
+            crate::jws::jwk_value_thumbprint(&protected["jwk"]).unwrap(),
+            crate::jws::jwk_thumbprint(&new_key).unwrap(),
+        );
+        assert!(protected.get("nonce").is_none());
+        assert!(protected.get("kid").is_none());
+
+        let payload = decode(&jws["payload"]);
+        assert_eq!(payload["account"], "https://example.com/acme/acct/1");
+        assert_eq!(
+            crate::jws::jwk_value_thumbprint(&payload["oldKey"]).unwrap(),
+            crate::jws::jwk_thumbprint(&old_key).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_is_valid_nonce() {
+        assert!(is_valid_nonce(
+            "8_uBBV3N2DBRJczhoiB46ugJKUkUHxGzVe6xIMpjHFM"
+        ));
+        assert!(!is_valid_nonce(""));
+        assert!(!is_valid_nonce(
+            "8_uBBV3N2DBRJczhoiB46ugJKUkUHxGzVe6xIMpjHFM="
+        ));
+        assert!(!is_valid_nonce("not a nonce"));
+    }
+
+    #[test]
+    fn test_nonce_pool_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<NoncePool>();
+        assert_send_sync::<Transport>();
+    }
+
+    #[tokio::test]
+    async fn test_get_nonce_falls_back_to_get() {
+        let server = crate::test::with_directory_server();
+        let url = server.dir_url.replace("/directory", "/acme/new-nonce-get");
 
-    let signature = BASE64_URL_SAFE_NO_PAD.encode(signature.to_bytes());
+        let pool = NoncePool::new(&url, HttpClient::new(reqwest::Client::new()));
+        assert!(!pool.get_nonce().await.unwrap().is_empty());
 
-    let jws = FlattenedJsonJws::new(header, payload, signature);
+        let url = server.dir_url.replace("/directory", "/acme/missing");
 
-    Ok(serde_json::to_string(&jws)?)
+        let pool = NoncePool::new(&url, HttpClient::new(reqwest::Client::new()));
+        assert!(pool.get_nonce().await.is_err());
+
+        let url = server
+            .dir_url
+            .replace("/directory", "/acme/new-nonce-missing");
+
+        let pool = NoncePool::new(&url, HttpClient::new(reqwest::Client::new()));
+        let err = pool.get_nonce().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingNonceHeader { url: nonce_url, status: 204 }) if nonce_url == &url,
+        ));
+    }
 }
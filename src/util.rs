@@ -0,0 +1,596 @@
+//! Utilities for inspecting issued certificates and converting keys.
+
+use std::{borrow::Cow, time::Duration};
+
+use base64::prelude::*;
+use der::{
+    asn1::{Any, OctetString, PrintableStringRef, Utf8StringRef},
+    oid::{db::rfc4519::COMMON_NAME, AssociatedOid as _},
+    Decode as _, Encode as _,
+};
+use pkcs8::{DecodePrivateKey as _, EncodePrivateKey as _, EncodePublicKey as _};
+use sha2::{Digest as _, Sha256};
+use x509_cert::ext::pkix::{name::GeneralName, SubjectAltName};
+use zeroize::Zeroizing;
+
+use crate::{api, Error, KeyKind};
+
+/// Returns true if the Subject Alternative Names of the DER-encoded certificate `cert_der` cover
+/// every identifier in `identifiers`.
+///
+/// Useful as a safety check after downloading a certificate, before deploying it. See
+/// [`missing_identifiers()`] to find out which identifiers are not covered.
+pub fn verify_certificate_covers(
+    cert_der: &[u8],
+    identifiers: &[api::Identifier],
+) -> eyre::Result<bool> {
+    Ok(missing_identifiers(cert_der, identifiers)?.is_empty())
+}
+
+/// Returns the identifiers from `identifiers` that are not covered by the Subject Alternative
+/// Names of the DER-encoded certificate `cert_der`.
+///
+/// DNS identifiers are compared case-insensitively.
+pub fn missing_identifiers(
+    cert_der: &[u8],
+    identifiers: &[api::Identifier],
+) -> eyre::Result<Vec<api::Identifier>> {
+    let names = subject_alt_names(cert_der)?;
+
+    Ok(identifiers
+        .iter()
+        .filter(|id| {
+            !names.iter().any(|name| match name {
+                GeneralName::DnsName(dns) => {
+                    id.is_type_dns() && dns.as_str().eq_ignore_ascii_case(&id.value)
+                }
+                GeneralName::IpAddress(ip) => id._type == "ip" && ip_matches(ip, &id.value),
+                _ => false,
+            })
+        })
+        .cloned()
+        .collect())
+}
+
+/// Returns the validity period (`notAfter - notBefore`) of the DER-encoded certificate.
+///
+/// ACME API providers may grant a shorter validity period than requested, especially for
+/// short-lived certificate profiles, so prefer this over assuming a fixed lifetime when scheduling
+/// renewals. See also [`compare_lifetime()`].
+pub fn certificate_lifetime(cert_der: &[u8]) -> eyre::Result<Duration> {
+    let cert = x509_cert::Certificate::from_der(cert_der)?;
+    let validity = cert.tbs_certificate.validity;
+
+    validity
+        .not_after
+        .to_unix_duration()
+        .checked_sub(validity.not_before.to_unix_duration())
+        .ok_or_else(|| eyre::eyre!("Certificate notAfter is before notBefore"))
+}
+
+/// Returns the serial number of the DER-encoded certificate `cert_der` as uppercase hex.
+///
+/// Useful for correlating certificates with inventories and Certificate Transparency logs.
+pub fn certificate_serial(cert_der: &[u8]) -> eyre::Result<String> {
+    let cert = x509_cert::Certificate::from_der(cert_der)?;
+
+    Ok(cert
+        .tbs_certificate
+        .serial_number
+        .as_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect())
+}
+
+/// Returns the common name (CN) of the issuer of the DER-encoded certificate `cert_der`, if any.
+///
+/// See [`CertOrder::preferred_certificate_chain()`] for selecting a chain by the issuer of its
+/// topmost certificate.
+///
+/// [`CertOrder::preferred_certificate_chain()`]: crate::order::CertOrder::preferred_certificate_chain()
+pub fn certificate_issuer_common_name(cert_der: &[u8]) -> eyre::Result<Option<String>> {
+    let cert = x509_cert::Certificate::from_der(cert_der)?;
+
+    let Some(atv) = cert
+        .tbs_certificate
+        .issuer
+        .0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .find(|atv| atv.oid == COMMON_NAME)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(decode_directory_string(&atv.value)?))
+}
+
+/// Decodes a distinguished name attribute `value`, e.g. a Common Name, encoded as either a
+/// `UTF8String` or a `PrintableString`.
+pub(crate) fn decode_directory_string(value: &Any) -> der::Result<String> {
+    match value.decode_as::<Utf8StringRef<'_>>() {
+        Ok(string) => Ok(string.to_string()),
+        Err(_) => Ok(value.decode_as::<PrintableStringRef<'_>>()?.to_string()),
+    }
+}
+
+/// Returns the SHA-256 fingerprint of the DER-encoded certificate `cert_der`.
+pub fn certificate_sha256_fingerprint(cert_der: &[u8]) -> [u8; 32] {
+    Sha256::digest(cert_der).into()
+}
+
+/// Formats `fingerprint` as colon-separated uppercase hex, e.g., `AB:CD:EF`.
+///
+/// This is the format used by common tools, e.g., `openssl x509 -fingerprint`.
+pub fn format_fingerprint(fingerprint: &[u8]) -> String {
+    fingerprint
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Requested versus granted validity period of a certificate.
+///
+/// Returned from [`compare_lifetime()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifetimeComparison {
+    /// Validity period requested by the order, if one was requested.
+    pub requested: Option<Duration>,
+
+    /// Validity period of the issued certificate.
+    pub granted: Duration,
+}
+
+impl LifetimeComparison {
+    /// Returns true if the certificate was granted a shorter validity period than requested.
+    pub fn is_shorter_than_requested(&self) -> bool {
+        self.requested
+            .is_some_and(|requested| self.granted < requested)
+    }
+}
+
+/// Compares the validity period requested by `order` with that of the issued DER-encoded
+/// certificate.
+pub fn compare_lifetime(order: &api::Order, cert_der: &[u8]) -> eyre::Result<LifetimeComparison> {
+    Ok(LifetimeComparison {
+        requested: order.requested_lifetime()?,
+        granted: certificate_lifetime(cert_der)?,
+    })
+}
+
+/// Returns true if the public key of the DER-encoded certificate `cert_der` corresponds to the
+/// PEM-encoded (PKCS #8) private key `key_pem`.
+///
+/// Useful as a safety check before deploying a certificate, to catch mixing up keys between orders.
+/// Fails with [`Error::UnsupportedKey`] if the private key is of a kind not supported by this
+/// library.
+///
+/// [`Error::UnsupportedKey`]: crate::Error::UnsupportedKey
+pub fn key_matches_certificate(key_pem: &str, cert_der: &[u8]) -> eyre::Result<bool> {
+    let cert = x509_cert::Certificate::from_der(cert_der)?;
+    let cert_spki = cert.tbs_certificate.subject_public_key_info.to_der()?;
+
+    let key_spki = match KeyKind::of_pem(key_pem)? {
+        KeyKind::EcdsaP256 => p256::SecretKey::from_pkcs8_pem(key_pem)?
+            .public_key()
+            .to_public_key_der()?,
+        KeyKind::EcdsaP384 => p384::SecretKey::from_pkcs8_pem(key_pem)?
+            .public_key()
+            .to_public_key_der()?,
+    };
+
+    Ok(key_spki.as_bytes() == cert_spki)
+}
+
+/// Converts a PEM-encoded (PKCS #8) private key, such as [`Certificate::private_key()`], to a
+/// private JWK.
+///
+/// Fails with [`Error::UnsupportedKey`] if the key is not of a [`KeyKind`] supported by this
+/// library.
+///
+/// [`Certificate::private_key()`]: crate::Certificate::private_key()
+pub fn pem_to_jwk(private_key_pem: &str) -> eyre::Result<Zeroizing<String>> {
+    Ok(match KeyKind::of_pem(private_key_pem)? {
+        KeyKind::EcdsaP256 => p256::SecretKey::from_pkcs8_pem(private_key_pem)?.to_jwk_string(),
+        KeyKind::EcdsaP384 => p384::SecretKey::from_pkcs8_pem(private_key_pem)?.to_jwk_string(),
+    })
+}
+
+/// Converts a private JWK to a PEM-encoded (PKCS #8) private key.
+///
+/// Fails with [`Error::UnsupportedKey`] if the key is not on a curve of a [`KeyKind`] supported by
+/// this library.
+pub fn jwk_to_pem(jwk: &str) -> eyre::Result<Zeroizing<String>> {
+    let jwk = jwk.parse::<p256::elliptic_curve::JwkEcKey>()?;
+
+    Ok(match jwk.crv() {
+        "P-256" => p256::SecretKey::from_jwk(&jwk)?.to_pkcs8_pem(pkcs8::LineEnding::LF)?,
+        "P-384" => p384::SecretKey::from_jwk(&jwk)?.to_pkcs8_pem(pkcs8::LineEnding::LF)?,
+        crv => {
+            return Err(Error::UnsupportedKey {
+                algorithm: format!("ECDSA {crv}"),
+            }
+            .into())
+        }
+    })
+}
+
+/// Splits a PEM file containing both a private key and a certificate chain, as written by some
+/// other ACME clients, into the private key and the certificates.
+///
+/// Certificates are returned in the order they appear. Other kinds of PEM blocks are ignored. Fails
+/// if the file does not contain exactly one private key and at least one certificate.
+///
+/// The results can be passed to [`Certificate::parse()`] after joining the certificates.
+///
+/// [`Certificate::parse()`]: crate::Certificate::parse()
+pub fn split_pem(pem: &str) -> eyre::Result<(Zeroizing<String>, Vec<String>)> {
+    let mut private_keys = Vec::new();
+    let mut certificates = Vec::new();
+
+    for (label, block) in pem_blocks(pem)? {
+        if label == "CERTIFICATE" {
+            certificates.push(block.to_string());
+        } else if label.ends_with("PRIVATE KEY") {
+            private_keys.push(block);
+        }
+    }
+
+    if private_keys.len() != 1 {
+        return Err(eyre::eyre!(
+            "Expected exactly one private key in PEM file but found {}",
+            private_keys.len()
+        ));
+    }
+
+    if certificates.is_empty() {
+        return Err(eyre::eyre!(
+            "Expected a certificate in PEM file but found none"
+        ));
+    }
+
+    Ok((private_keys.remove(0), certificates))
+}
+
+/// Splits a concatenated PEM document into its blocks, returning the label of each block and the
+/// block re-encoded from its decoded contents, ending in a newline.
+///
+/// Text outside the blocks is ignored. Fails if a block is not terminated or is not valid PEM.
+pub(crate) fn pem_blocks(pem: &str) -> eyre::Result<Vec<(String, Zeroizing<String>)>> {
+    let mut blocks = Vec::new();
+    let mut lines = pem.lines();
+
+    while let Some(line) = lines.next() {
+        let Some(label) = line
+            .trim()
+            .strip_prefix("-----BEGIN ")
+            .and_then(|rest| rest.strip_suffix("-----"))
+        else {
+            continue;
+        };
+
+        let end = format!("-----END {label}-----");
+        let mut block = Zeroizing::new(format!("{}\n", line.trim()));
+
+        loop {
+            let line = lines
+                .next()
+                .ok_or_else(|| eyre::eyre!("PEM block \"{label}\" is not terminated"))?
+                .trim();
+
+            block.push_str(line);
+            block.push('\n');
+
+            if line == end {
+                break;
+            }
+        }
+
+        let (_label, der) = pem::decode_vec(block.as_bytes())
+            .map_err(|err| eyre::eyre!("Failed to decode PEM block \"{label}\": {err}"))?;
+        let der = Zeroizing::new(der);
+
+        let block = pem::encode_string(label, pem::LineEnding::LF, &der)
+            .map_err(|err| eyre::eyre!("Failed to encode PEM block \"{label}\": {err}"))?;
+
+        blocks.push((label.to_owned(), Zeroizing::new(block)));
+    }
+
+    Ok(blocks)
+}
+
+/// Returns a new random key for [`Account::new_order_with_idempotency_key()`].
+///
+/// The key is 128 random bits, base64url-encoded. Generate one key for each logical order and
+/// reuse it when retrying the order's creation.
+///
+/// [`Account::new_order_with_idempotency_key()`]: crate::Account::new_order_with_idempotency_key()
+pub fn generate_idempotency_key() -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(rand::random::<[u8; 16]>())
+}
+
+/// Returns true if `value` is a non-empty base64url string without padding, as used for ACME nonces
+/// and challenge tokens.
+pub(crate) fn is_base64url(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Converts `domain` to its ASCII (A-label, or "punycode") form, as required for ACME identifiers
+/// and certificates.
+///
+/// Returns [`Cow::Borrowed`] if `domain` is already ASCII, in which case it is left untouched, and
+/// [`Cow::Owned`] if it contained non-ASCII (U-label) characters and was converted. A leading
+/// wildcard label (`*.`) is preserved. Fails with [`Error::InvalidDomainName`] if `domain` is not
+/// a valid internationalized domain name.
+///
+/// This is applied to all domain names passed to [`Account::new_order()`], so the converted names
+/// can be found in the order's identifiers.
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// let domain = acme::util::domain_to_ascii("bücher.example").unwrap();
+/// assert_eq!(domain, Cow::<str>::Owned("xn--bcher-kva.example".to_owned()));
+///
+/// let domain = acme::util::domain_to_ascii("*.example.org").unwrap();
+/// assert_eq!(domain, Cow::Borrowed("*.example.org"));
+/// ```
+///
+/// [`Account::new_order()`]: crate::Account::new_order()
+pub fn domain_to_ascii(domain: &str) -> eyre::Result<Cow<'_, str>> {
+    if domain.is_ascii() {
+        return Ok(Cow::Borrowed(domain));
+    }
+
+    let (wildcard, name) = match domain.strip_prefix("*.") {
+        Some(name) => ("*.", name),
+        None => ("", domain),
+    };
+
+    let ascii = idna::domain_to_ascii_strict(name).map_err(|_| Error::InvalidDomainName {
+        domain: domain.to_owned(),
+    })?;
+
+    Ok(Cow::Owned(format!("{wildcard}{ascii}")))
+}
+
+pub(crate) fn subject_alt_names(cert_der: &[u8]) -> eyre::Result<Vec<GeneralName>> {
+    let cert = x509_cert::Certificate::from_der(cert_der)?;
+
+    let Some(ext) = cert
+        .tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .find(|ext| ext.extn_id == SubjectAltName::OID)
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(SubjectAltName::from_der(ext.extn_value.as_bytes())?.0)
+}
+
+fn ip_matches(ip: &OctetString, value: &str) -> bool {
+    match value.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(addr)) => ip.as_bytes() == addr.octets(),
+        Ok(std::net::IpAddr::V6(addr)) => ip.as_bytes() == addr.octets(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certificate_issuer_common_name() {
+        let cert = crate::test::self_signed_certificate_with_cn("Alternate Root X1");
+        let cert_der = cert.certificate_chain().unwrap().remove(0);
+
+        assert_eq!(
+            certificate_issuer_common_name(&cert_der)
+                .unwrap()
+                .as_deref(),
+            Some("Alternate Root X1"),
+        );
+    }
+
+    #[test]
+    fn test_missing_identifiers() {
+        let cert = rcgen::generate_simple_self_signed(vec![
+            "example.com".to_owned(),
+            "www.example.com".to_owned(),
+        ])
+        .unwrap()
+        .cert;
+
+        let covered = [
+            api::Identifier::dns("example.com"),
+            api::Identifier::dns("WWW.example.com"),
+        ];
+        assert!(verify_certificate_covers(cert.der(), &covered).unwrap());
+
+        let uncovered = [
+            api::Identifier::dns("example.com"),
+            api::Identifier::dns("mail.example.com"),
+        ];
+        assert!(!verify_certificate_covers(cert.der(), &uncovered).unwrap());
+        assert_eq!(
+            missing_identifiers(cert.der(), &uncovered).unwrap(),
+            [api::Identifier::dns("mail.example.com")],
+        );
+    }
+
+    #[test]
+    fn test_key_matches_certificate() {
+        let params = rcgen::CertificateParams::new(vec!["example.com".to_owned()]).unwrap();
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let cert = params.clone().self_signed(&key_pair).unwrap();
+
+        let key_pem = key_pair.serialize_pem();
+        assert!(key_matches_certificate(&key_pem, cert.der()).unwrap());
+
+        let other_pem = crate::create_p256_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+        assert!(!key_matches_certificate(&other_pem, cert.der()).unwrap());
+
+        let p384_pair = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P384_SHA384).unwrap();
+        let p384_cert = params.clone().self_signed(&p384_pair).unwrap();
+        assert!(!key_matches_certificate(&key_pem, p384_cert.der()).unwrap());
+        assert!(key_matches_certificate(&p384_pair.serialize_pem(), p384_cert.der()).unwrap());
+
+        let ed25519_pair = rcgen::KeyPair::generate_for(&rcgen::PKCS_ED25519).unwrap();
+        let ed25519_cert = params.self_signed(&ed25519_pair).unwrap();
+        assert!(
+            key_matches_certificate(&ed25519_pair.serialize_pem(), ed25519_cert.der()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_split_pem() {
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let params = rcgen::CertificateParams::new(vec!["example.com".to_owned()]).unwrap();
+        let cert = params.self_signed(&key_pair).unwrap();
+        let issuer = rcgen::generate_simple_self_signed(vec!["ca.example.com".to_owned()])
+            .unwrap()
+            .cert;
+
+        let key_pem = key_pair.serialize_pem();
+        let combined = format!("{key_pem}{}{}", cert.pem(), issuer.pem());
+
+        let (private_key, certificates) = split_pem(&combined).unwrap();
+        assert_eq!(*private_key, key_pem);
+        assert_eq!(certificates, [cert.pem(), issuer.pem()]);
+
+        let two_keys = format!("{key_pem}{key_pem}{}", cert.pem());
+        assert!(split_pem(&two_keys).is_err());
+        assert!(split_pem(&key_pem).is_err());
+        assert!(split_pem(&cert.pem()).is_err());
+    }
+
+    #[test]
+    fn test_generate_idempotency_key() {
+        let key = generate_idempotency_key();
+        assert_eq!(key.len(), 22);
+        assert!(is_base64url(&key));
+        assert_ne!(key, generate_idempotency_key());
+    }
+
+    #[test]
+    fn test_is_base64url() {
+        assert!(is_base64url("LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0"));
+        assert!(!is_base64url(""));
+        assert!(!is_base64url(
+            "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0="
+        ));
+        assert!(!is_base64url("../../etc/passwd"));
+    }
+
+    #[test]
+    fn test_domain_to_ascii() {
+        assert_eq!(
+            domain_to_ascii("example.com").unwrap(),
+            Cow::Borrowed("example.com")
+        );
+        assert_eq!(
+            domain_to_ascii("xn--bcher-kva.example").unwrap(),
+            Cow::Borrowed("xn--bcher-kva.example")
+        );
+        assert_eq!(
+            domain_to_ascii("BÜCHER.example").unwrap(),
+            Cow::<str>::Owned("xn--bcher-kva.example".to_owned())
+        );
+        assert_eq!(
+            domain_to_ascii("*.münchen.example").unwrap(),
+            Cow::<str>::Owned("*.xn--mnchen-3ya.example".to_owned())
+        );
+
+        let err = domain_to_ascii("bücher..example").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidDomainName { domain }) if domain == "bücher..example"
+        ));
+    }
+
+    #[test]
+    fn test_pem_jwk_round_trip() {
+        let pem = crate::create_p256_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+
+        let jwk = pem_to_jwk(&pem).unwrap();
+        let jwk_json = serde_json::from_str::<serde_json::Value>(&jwk).unwrap();
+        assert_eq!(jwk_json["kty"], "EC");
+        assert_eq!(jwk_json["crv"], "P-256");
+        assert!(jwk_json["d"].is_string());
+
+        assert_eq!(jwk_to_pem(&jwk).unwrap(), pem);
+    }
+
+    #[test]
+    fn test_pem_jwk_round_trip_p384() {
+        let pem = crate::create_p384_key()
+            .to_pkcs8_pem(pkcs8::LineEnding::LF)
+            .unwrap();
+
+        let jwk = pem_to_jwk(&pem).unwrap();
+        let jwk_json = serde_json::from_str::<serde_json::Value>(&jwk).unwrap();
+        assert_eq!(jwk_json["crv"], "P-384");
+
+        assert_eq!(jwk_to_pem(&jwk).unwrap(), pem);
+
+        let p521 = jwk.replace("P-384", "P-521");
+        assert!(matches!(
+            jwk_to_pem(&p521).unwrap_err().downcast_ref::<Error>(),
+            Some(Error::UnsupportedKey { algorithm }) if algorithm == "ECDSA P-521",
+        ));
+    }
+
+    #[test]
+    fn test_certificate_serial_and_fingerprint() {
+        let (_label, cert_der) =
+            pem::decode_vec(crate::test::FIXTURE_CERTIFICATE_PEM.as_bytes()).unwrap();
+
+        assert_eq!(certificate_serial(&cert_der).unwrap(), "01ABCD");
+        assert_eq!(
+            format_fingerprint(&certificate_sha256_fingerprint(&cert_der)),
+            crate::test::FIXTURE_CERTIFICATE_SHA256,
+        );
+    }
+
+    #[test]
+    fn test_format_fingerprint() {
+        assert_eq!(format_fingerprint(&[]), "");
+        assert_eq!(format_fingerprint(&[0x01, 0xAB, 0xCD]), "01:AB:CD");
+    }
+
+    #[test]
+    fn test_compare_lifetime() {
+        let mut params = rcgen::CertificateParams::new(vec!["example.com".to_owned()]).unwrap();
+        params.not_before = rcgen::date_time_ymd(2024, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2024, 1, 7);
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key_pair).unwrap();
+
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+        assert_eq!(certificate_lifetime(cert.der()).unwrap(), 6 * DAY);
+
+        let order = api::Order {
+            not_before: Some("2024-01-01T00:00:00Z".to_owned()),
+            not_after: Some("2024-01-31T00:00:00Z".to_owned()),
+            ..Default::default()
+        };
+
+        let comparison = compare_lifetime(&order, cert.der()).unwrap();
+        assert_eq!(comparison.requested, Some(30 * DAY));
+        assert_eq!(comparison.granted, 6 * DAY);
+        assert!(comparison.is_shorter_than_requested());
+    }
+}